@@ -0,0 +1,53 @@
+use assert_fs::prelude::{FileWriteBin, PathChild};
+use assert_fs::TempDir;
+use criterion::{criterion_group, criterion_main, Criterion};
+use std::path::Path;
+use std::process::Command;
+
+const DIRS: usize = 20;
+const FILES: usize = 2000;
+const FILE_SIZE: usize = 16 * 1024;
+
+fn run_vcs(repo: &Path, args: &[&str], threads: Option<usize>) {
+    let mut cmd = Command::new(env!("CARGO_BIN_EXE_vcs"));
+    cmd.current_dir(repo).args(args);
+    if let Some(threads) = threads {
+        cmd.env("RAYON_NUM_THREADS", threads.to_string());
+    }
+    let output = cmd.output().unwrap();
+    assert!(output.status.success(), "{output:?}");
+}
+
+fn write_files(repo: &TempDir, seed: u8) {
+    for i in 0..FILES {
+        let data: Vec<u8> = (0..FILE_SIZE).map(|j| (i + j) as u8 ^ seed).collect();
+        repo.child(format!("dir{}/file{i}", i % DIRS))
+            .write_binary(&data)
+            .unwrap();
+    }
+}
+
+/// Creates a repository where every tracked file is modified, so that status
+/// has to hash all of them.
+fn create_modified_repo() -> TempDir {
+    let repo = TempDir::new().unwrap();
+    write_files(&repo, 0);
+    run_vcs(repo.path(), &["init", "--path", "."], None);
+    write_files(&repo, 1);
+    repo
+}
+
+fn bench_status(c: &mut Criterion) {
+    let repo = create_modified_repo();
+    let mut group = c.benchmark_group("status");
+    group.sample_size(10);
+    for (name, threads) in [("sequential", Some(1)), ("parallel", None)] {
+        group.bench_function(name, |b| {
+            b.iter(|| run_vcs(repo.path(), &["status"], threads))
+        });
+    }
+    group.finish();
+}
+
+criterion_group!(benches, bench_status);
+criterion_main!(benches);
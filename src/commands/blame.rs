@@ -0,0 +1,17 @@
+use super::get_repo_path;
+use crate::parser::OutputFormat;
+use crate::report_printer::{display_blame, report_json};
+
+use std::path::Path;
+use vcs::{Repository, VcsResult};
+
+pub fn run(path: &Path, rev: Option<&str>, format: OutputFormat) -> VcsResult<String> {
+    let current_dir = std::env::current_dir()?.canonicalize()?;
+    let repo = Repository::open(&current_dir)?;
+    let path = get_repo_path(repo.root(), &current_dir, path)?;
+    let blame = repo.blame(&path, rev)?;
+    match format {
+        OutputFormat::Text => Ok(display_blame(&blame, &repo.date_format())),
+        OutputFormat::Json => report_json(&blame),
+    }
+}
@@ -0,0 +1,16 @@
+use crate::parser::OutputFormat;
+use crate::report_printer::{report_json, report_object, report_object_type};
+
+use serde_json::json;
+use vcs::{Repository, VcsResult};
+
+pub fn run(object: &str, show_type: bool, format: OutputFormat) -> VcsResult<String> {
+    let repo = Repository::open(&std::env::current_dir()?)?;
+    let info = repo.object(object)?;
+    match (format, show_type) {
+        (OutputFormat::Text, true) => Ok(report_object_type(&info)),
+        (OutputFormat::Text, false) => Ok(report_object(&info, &repo.date_format())),
+        (OutputFormat::Json, true) => report_json(&json!({ "type": info.kind() })),
+        (OutputFormat::Json, false) => report_json(&info),
+    }
+}
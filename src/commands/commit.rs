@@ -1,9 +1,13 @@
-use crate::errors::VcsResult;
-use crate::report_printer::report_successful_commit;
-use crate::vcs_manager;
+use crate::parser::OutputFormat;
+use crate::report_printer::{report_json, report_successful_commit};
 
-pub fn run(message: &str) -> VcsResult<String> {
-    let repo = vcs_manager::find_repository(&std::env::current_dir()?)?;
-    let info = vcs_manager::make_commit(&repo, message)?;
-    Ok(report_successful_commit(&info))
+use vcs::{Repository, VcsResult};
+
+pub fn run(message: &str, format: OutputFormat) -> VcsResult<String> {
+    let repo = Repository::open(&std::env::current_dir()?)?;
+    let info = repo.commit(message)?;
+    match format {
+        OutputFormat::Text => Ok(report_successful_commit(&info)),
+        OutputFormat::Json => report_json(&info),
+    }
 }
@@ -0,0 +1,24 @@
+use crate::parser::OutputFormat;
+use crate::report_printer::report_json;
+
+use serde_json::json;
+use vcs::{Repository, VcsResult};
+
+pub fn run(key: &str, value: Option<&str>, format: OutputFormat) -> VcsResult<String> {
+    let mut repo = Repository::open(&std::env::current_dir()?)?;
+    if let Some(value) = value {
+        repo.set_setting(key, value)?;
+        if format == OutputFormat::Text {
+            return Ok(String::new());
+        }
+    }
+    let value = repo.setting(key)?;
+    match format {
+        // Strings are printed as they are, other values in json format.
+        OutputFormat::Text => Ok(match value {
+            serde_json::Value::String(value) => value + "\n",
+            value => format!("{value}\n"),
+        }),
+        OutputFormat::Json => report_json(&json!({ "key": key, "value": value })),
+    }
+}
@@ -0,0 +1,15 @@
+use crate::parser::OutputFormat;
+use crate::report_printer::{report_integrity_issues, report_json};
+
+use serde_json::json;
+use vcs::{Repository, VcsResult};
+
+pub fn run(format: OutputFormat) -> VcsResult<String> {
+    let repo = Repository::open(&std::env::current_dir()?)?;
+    let issues = repo.check()?;
+    match format {
+        OutputFormat::Text if issues.is_empty() => Ok("No problems found\n".to_string()),
+        OutputFormat::Text => Ok(report_integrity_issues(&issues)),
+        OutputFormat::Json => report_json(&json!({ "issues": issues })),
+    }
+}
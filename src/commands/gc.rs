@@ -0,0 +1,14 @@
+use crate::parser::OutputFormat;
+use crate::report_printer::{report_garbage_collection, report_json};
+
+use std::time::Duration;
+use vcs::{Repository, VcsResult};
+
+pub fn run(grace_period: Duration, format: OutputFormat) -> VcsResult<String> {
+    let repo = Repository::open(&std::env::current_dir()?)?;
+    let info = repo.collect_garbage(grace_period)?;
+    match format {
+        OutputFormat::Text => Ok(report_garbage_collection(&info)),
+        OutputFormat::Json => report_json(&info),
+    }
+}
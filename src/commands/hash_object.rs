@@ -0,0 +1,15 @@
+use crate::parser::OutputFormat;
+use crate::report_printer::{report_json, report_object_id};
+
+use serde_json::json;
+use std::path::Path;
+use vcs::{Repository, VcsResult};
+
+pub fn run(file: &Path, write: bool, format: OutputFormat) -> VcsResult<String> {
+    let repo = Repository::open(&std::env::current_dir()?)?;
+    let human_id = repo.hash_file(file, write)?;
+    match format {
+        OutputFormat::Text => Ok(report_object_id(&human_id)),
+        OutputFormat::Json => report_json(&json!({ "id": human_id })),
+    }
+}
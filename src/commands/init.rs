@@ -1,16 +1,20 @@
-use std::path::Path;
+use crate::parser::OutputFormat;
+use crate::report_printer::{report_init_json, report_successful_commit, report_successful_init};
 
-use crate::errors::VcsResult;
-use crate::report_printer::{report_successful_commit, report_successful_init};
-use crate::vcs_manager;
+use std::path::Path;
+use vcs::{HashAlgorithm, ObjectStoreKind, Repository, VcsResult};
 
-pub fn run(path: &Path) -> VcsResult<String> {
-    let path = path.to_path_buf().canonicalize()?;
-    let mut report = match vcs_manager::init_vcs_directory(&path) {
-        Ok(_) => report_successful_init(path.to_str().unwrap()),
-        Err(err) => return Err(err),
-    };
-    let info = vcs_manager::make_commit(&path, "Initial commit")?;
+pub fn run(
+    path: &Path,
+    hash: HashAlgorithm,
+    object_store: ObjectStoreKind,
+    format: OutputFormat,
+) -> VcsResult<String> {
+    let (repo, info) = Repository::init(path, hash, object_store)?;
+    if format == OutputFormat::Json {
+        return report_init_json(repo.root(), &info);
+    }
+    let mut report = report_successful_init(repo.root());
     report += "Created commit:\n";
     report += &report_successful_commit(&info);
     Ok(report)
@@ -1,15 +1,16 @@
-use crate::errors::VcsResult;
-use crate::report_printer::{report_successful_jump_to_branch, report_successful_jump_to_commit};
-use crate::vcs_manager;
+use crate::parser::OutputFormat;
+use crate::report_printer::{
+    report_json, report_successful_jump_to_branch, report_successful_jump_to_commit,
+};
 
-pub fn to_commit(id: &str) -> VcsResult<String> {
-    let repo = vcs_manager::find_repository(&std::env::current_dir()?)?;
-    let state = vcs_manager::jump_to_commit(&repo, id)?;
-    Ok(report_successful_jump_to_commit(&state))
-}
+use vcs::{JumpTarget, Repository, VcsResult};
 
-pub fn to_branch(branch_name: &str) -> VcsResult<String> {
-    let repo = vcs_manager::find_repository(&std::env::current_dir()?)?;
-    let state = vcs_manager::jump_to_branch(&repo, branch_name)?;
-    Ok(report_successful_jump_to_branch(&state))
+pub fn run(target: JumpTarget, format: OutputFormat) -> VcsResult<String> {
+    let repo = Repository::open(&std::env::current_dir()?)?;
+    let state = repo.jump(target)?;
+    match (format, target) {
+        (OutputFormat::Text, JumpTarget::Branch(_)) => Ok(report_successful_jump_to_branch(&state)),
+        (OutputFormat::Text, JumpTarget::Commit(_)) => Ok(report_successful_jump_to_commit(&state)),
+        (OutputFormat::Json, _) => report_json(&state),
+    }
 }
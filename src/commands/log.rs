@@ -1,9 +1,41 @@
-use crate::errors::VcsResult;
-use crate::report_printer::display_logs;
-use crate::vcs_manager;
+use super::get_repo_path;
+use crate::parser::OutputFormat;
+use crate::report_printer::{display_graph, display_logs, report_json, LogStyle};
 
-pub fn run() -> VcsResult<String> {
-    let repo = vcs_manager::find_repository(&std::env::current_dir()?)?;
-    let logs = vcs_manager::get_commit_logs(&repo)?;
-    Ok(display_logs(&logs))
+use std::path::PathBuf;
+use vcs::{DateFormat, LogFilter, Repository, VcsResult};
+
+/// How the logs are shown.
+pub struct LogView {
+    pub graph: bool,
+    /// Ignored in json format.
+    pub style: LogStyle,
+    pub stat: bool,
+    /// Overrides the date_format setting. Ignored in json format.
+    pub date: Option<DateFormat>,
+}
+
+pub fn run(
+    mut filter: LogFilter,
+    paths: &[PathBuf],
+    view: &LogView,
+    format: OutputFormat,
+) -> VcsResult<String> {
+    let current_dir = std::env::current_dir()?.canonicalize()?;
+    let repo = Repository::open(&current_dir)?;
+    for path in paths {
+        filter
+            .paths
+            .push(get_repo_path(repo.root(), &current_dir, path)?);
+    }
+    let mut logs = repo.log(&filter)?;
+    if view.stat {
+        repo.add_stats(&mut logs)?;
+    }
+    let date_format = view.date.clone().unwrap_or_else(|| repo.date_format());
+    match format {
+        OutputFormat::Text if view.graph => Ok(display_graph(&logs, &view.style, &date_format)),
+        OutputFormat::Text => Ok(display_logs(&logs, &view.style, &date_format)),
+        OutputFormat::Json => report_json(&logs),
+    }
 }
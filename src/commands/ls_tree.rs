@@ -0,0 +1,13 @@
+use crate::parser::OutputFormat;
+use crate::report_printer::{report_json, report_tree_entries};
+
+use vcs::{Repository, VcsResult};
+
+pub fn run(tree_ish: &str, recursive: bool, format: OutputFormat) -> VcsResult<String> {
+    let repo = Repository::open(&std::env::current_dir()?)?;
+    let entries = repo.list_tree(tree_ish, recursive)?;
+    match format {
+        OutputFormat::Text => Ok(report_tree_entries(&entries)),
+        OutputFormat::Json => report_json(&entries),
+    }
+}
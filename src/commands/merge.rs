@@ -1,11 +1,17 @@
-use crate::errors::VcsResult;
-use crate::report_printer::report_successful_commit;
-use crate::vcs_manager;
+use crate::parser::OutputFormat;
+use crate::report_printer::{report_json, report_successful_commit};
 
-pub fn run(branch: &str) -> VcsResult<String> {
-    let repo = vcs_manager::find_repository(&std::env::current_dir()?)?;
-    let info = vcs_manager::merge_branch(&repo, branch)?;
-    let report =
-        "Successfully created merge commit:\n".to_string() + &report_successful_commit(&info);
-    Ok(report)
+use vcs::{Repository, VcsResult};
+
+pub fn run(branch: &str, format: OutputFormat) -> VcsResult<String> {
+    let repo = Repository::open(&std::env::current_dir()?)?;
+    let info = repo.merge(branch)?;
+    match format {
+        OutputFormat::Text => {
+            let report = "Successfully created merge commit:\n".to_string()
+                + &report_successful_commit(&info);
+            Ok(report)
+        }
+        OutputFormat::Json => report_json(&info),
+    }
 }
@@ -1,7 +1,37 @@
+pub mod blame;
+pub mod cat_object;
 pub mod commit;
+pub mod config;
+pub mod fsck;
+pub mod gc;
+pub mod hash_object;
 pub mod init;
 pub mod jump;
 pub mod log;
+pub mod ls_tree;
 pub mod merge;
 pub mod new_branch;
 pub mod status;
+
+use std::path::{Component, Path, PathBuf};
+use vcs::{VcsError, VcsResult};
+
+/// Turns a path relative to the current directory into one relative to the
+/// repository root. The path doesn't need to exist, as it may have been
+/// removed from the working tree.
+pub fn get_repo_path(root: &Path, current_dir: &Path, path: &Path) -> VcsResult<PathBuf> {
+    let mut absolute = PathBuf::new();
+    for component in current_dir.join(path).components() {
+        match component {
+            Component::CurDir => (),
+            Component::ParentDir => {
+                absolute.pop();
+            }
+            component => absolute.push(component),
+        }
+    }
+    match absolute.strip_prefix(root) {
+        Ok(relative) => Ok(relative.to_owned()),
+        Err(_) => Err(VcsError::OutsideRepository(path.to_owned()).into()),
+    }
+}
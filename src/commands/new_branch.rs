@@ -1,10 +1,13 @@
-use crate::errors::VcsResult;
-use crate::vcs_manager;
+use crate::parser::OutputFormat;
+use crate::report_printer::{report_creating_new_branch, report_json};
 
-use crate::report_printer::report_creating_new_branch;
+use vcs::{Repository, VcsResult};
 
-pub fn run(name: &str) -> VcsResult<String> {
-    let repo = vcs_manager::find_repository(&std::env::current_dir()?)?;
-    let info = vcs_manager::create_branch(&repo, name)?;
-    Ok(report_creating_new_branch(&info))
+pub fn run(name: &str, format: OutputFormat) -> VcsResult<String> {
+    let repo = Repository::open(&std::env::current_dir()?)?;
+    let info = repo.create_branch(name)?;
+    match format {
+        OutputFormat::Text => Ok(report_creating_new_branch(&info)),
+        OutputFormat::Json => report_json(&info),
+    }
 }
@@ -1,12 +1,16 @@
-use crate::errors::VcsResult;
-use crate::report_printer::{report_changes, report_current_branch};
-use crate::vcs_manager;
+use crate::parser::OutputFormat;
+use crate::report_printer::{report_changes, report_current_branch, report_status_json};
 
-pub fn run() -> VcsResult<String> {
-    let repo = vcs_manager::find_repository(&std::env::current_dir()?)?;
-    let state = vcs_manager::get_state(&repo)?;
+use vcs::{Repository, VcsResult};
+
+pub fn run(format: OutputFormat) -> VcsResult<String> {
+    let repo = Repository::open(&std::env::current_dir()?)?;
+    let state = repo.state()?;
+    let changes = repo.status()?;
+    if format == OutputFormat::Json {
+        return report_status_json(&state.branch, &changes);
+    }
     let mut report = report_current_branch(&state.branch);
-    let changes = vcs_manager::get_changes(&repo)?;
     if changes.is_empty() {
         report += "No changes to be committed\n";
     } else {
@@ -1,10 +1,27 @@
-use crate::report_printer::report_changes;
-use crate::vcs_manager::FileChanges;
+use crate::vcs_manager::{escape_path, serialize_changes, FileChanges, FileStatus, IntegrityIssue};
 
+use serde::Serialize;
+use serde_json::{json, Map, Value};
+use std::path::PathBuf;
 use thiserror::Error;
 
 pub type VcsResult<T> = Result<T, anyhow::Error>;
 
+/// Lists the changes in an error message, one per line.
+fn format_changes(changes: &[(FileStatus, PathBuf)]) -> String {
+    let mut message = String::new();
+    for (status, path) in changes.iter() {
+        message += &format!("  {status}: {}\n", escape_path(path));
+    }
+    message
+}
+
+/// Lists the issues in an error message, one per line.
+fn format_issues(issues: &[IntegrityIssue]) -> String {
+    let issues: Vec<_> = issues.iter().map(IntegrityIssue::to_string).collect();
+    issues.join("\n")
+}
+
 #[derive(Debug, Error)]
 pub enum VcsError {
     #[error(transparent)]
@@ -37,7 +54,7 @@ pub enum VcsError {
             {}\
         Please commit your changes or drop them before you jump.\n\
         Aborting...",
-        report_changes(changes)
+        format_changes(changes)
     )]
     UncomittedChanges { changes: FileChanges },
     #[error(
@@ -54,7 +71,7 @@ pub enum VcsError {
         "Merge conflict: file(s) has been changed both in master and branch\n\
         {}\
         Aborting...",
-        report_changes(both_changed)
+        format_changes(both_changed)
     )]
     MergeConflict { both_changed: FileChanges },
     #[error(
@@ -62,6 +79,218 @@ pub enum VcsError {
         Aborting..."
     )]
     MergeFromNotMasterHead,
+    #[error("No object with hash {0} exists.")]
+    NoObject(String),
+    #[error("Object {id} is a {found}, not a {expected}.")]
+    WrongObjectType {
+        expected: &'static str,
+        found: &'static str,
+        id: String,
+    },
+    #[error("Object {0} is neither a tree nor a commit.")]
+    NotTreeish(String),
+    #[error("Path {} is outside repository.", escape_path(.0))]
+    OutsideRepository(PathBuf),
+    #[error(
+        "Repository integrity check failed:\n\
+        {}",
+        format_issues(issues)
+    )]
+    CorruptRepository { issues: Vec<IntegrityIssue> },
+    #[error(
+        "Another vcs process (pid {0}) is running in this repository.\n\
+        Aborting..."
+    )]
+    RepositoryLocked(u32),
+    #[error("Object {} is corrupted: its contents don't match its hash.", .0.display())]
+    CorruptObject(PathBuf),
+    #[error("No file {} exists in commit {commit}.", escape_path(path))]
+    NoFile { path: PathBuf, commit: String },
+    #[error("No setting {0} exists.")]
+    UnknownSetting(String),
+    #[error("Setting {0} can't be changed.")]
+    ReadOnlySetting(String),
+    #[error("Invalid value {value} for setting {key}.")]
+    InvalidSetting { key: String, value: String },
+}
+
+// Exit statuses of failed commands. They are documented in the README and
+// never change: 2 is left for invalid arguments, which are reported by clap.
+
+/// Exit status of the failures that are not described by a `VcsError`.
+const EXIT_OTHER: i32 = 1;
+/// Exit status of I/O failures, whether or not they are wrapped in a
+/// `VcsError`.
+const EXIT_IO: i32 = 3;
+const EXIT_JSON: i32 = 4;
+const EXIT_NOT_VCS_REPOSITORY: i32 = 10;
+const EXIT_ALREADY_VCS_REPOSITORY: i32 = 11;
+const EXIT_REPOSITORY_LOCKED: i32 = 12;
+const EXIT_NO_CHANGES: i32 = 20;
+const EXIT_COMMIT_FROM_NON_HEAD: i32 = 21;
+const EXIT_BRANCH_OFF_NON_MASTER: i32 = 22;
+const EXIT_BRANCH_ALREADY_EXISTS: i32 = 23;
+const EXIT_UNCOMITTED_CHANGES: i32 = 24;
+const EXIT_NO_COMMIT: i32 = 25;
+const EXIT_NO_BRANCH: i32 = 26;
+const EXIT_MERGE_CONFLICT: i32 = 27;
+const EXIT_MERGE_FROM_NOT_MASTER_HEAD: i32 = 28;
+const EXIT_NO_OBJECT: i32 = 30;
+const EXIT_WRONG_OBJECT_TYPE: i32 = 31;
+const EXIT_NOT_TREEISH: i32 = 32;
+const EXIT_OUTSIDE_REPOSITORY: i32 = 33;
+const EXIT_CORRUPT_REPOSITORY: i32 = 34;
+const EXIT_CORRUPT_OBJECT: i32 = 35;
+const EXIT_NO_FILE: i32 = 36;
+const EXIT_UNKNOWN_SETTING: i32 = 40;
+const EXIT_READ_ONLY_SETTING: i32 = 41;
+const EXIT_INVALID_SETTING: i32 = 42;
+
+impl VcsError {
+    /// Returns the name of the variant.
+    pub fn kind(&self) -> &'static str {
+        match self {
+            VcsError::Io(_) => "Io",
+            VcsError::Json(_) => "Json",
+            VcsError::NotVcsRepository => "NotVcsRepository",
+            VcsError::AlreadyVcsRepository => "AlreadyVcsRepository",
+            VcsError::NoChanges => "NoChanges",
+            VcsError::CommitFromNonHead => "CommitFromNonHead",
+            VcsError::BranchOffNonMaster => "BranchOffNonMaster",
+            VcsError::BranchAlreadyExists(_) => "BranchAlreadyExists",
+            VcsError::UncomittedChanges { .. } => "UncomittedChanges",
+            VcsError::NoCommit(_) => "NoCommit",
+            VcsError::NoBranch(_) => "NoBranch",
+            VcsError::MergeConflict { .. } => "MergeConflict",
+            VcsError::MergeFromNotMasterHead => "MergeFromNotMasterHead",
+            VcsError::NoObject(_) => "NoObject",
+            VcsError::WrongObjectType { .. } => "WrongObjectType",
+            VcsError::NotTreeish(_) => "NotTreeish",
+            VcsError::OutsideRepository(_) => "OutsideRepository",
+            VcsError::CorruptRepository { .. } => "CorruptRepository",
+            VcsError::RepositoryLocked(_) => "RepositoryLocked",
+            VcsError::CorruptObject(_) => "CorruptObject",
+            VcsError::NoFile { .. } => "NoFile",
+            VcsError::UnknownSetting(_) => "UnknownSetting",
+            VcsError::ReadOnlySetting(_) => "ReadOnlySetting",
+            VcsError::InvalidSetting { .. } => "InvalidSetting",
+        }
+    }
+
+    /// Returns the exit status of a command failing with the error.
+    pub fn exit_code(&self) -> i32 {
+        match self {
+            VcsError::Io(_) => EXIT_IO,
+            VcsError::Json(_) => EXIT_JSON,
+            VcsError::NotVcsRepository => EXIT_NOT_VCS_REPOSITORY,
+            VcsError::AlreadyVcsRepository => EXIT_ALREADY_VCS_REPOSITORY,
+            VcsError::RepositoryLocked(_) => EXIT_REPOSITORY_LOCKED,
+            VcsError::NoChanges => EXIT_NO_CHANGES,
+            VcsError::CommitFromNonHead => EXIT_COMMIT_FROM_NON_HEAD,
+            VcsError::BranchOffNonMaster => EXIT_BRANCH_OFF_NON_MASTER,
+            VcsError::BranchAlreadyExists(_) => EXIT_BRANCH_ALREADY_EXISTS,
+            VcsError::UncomittedChanges { .. } => EXIT_UNCOMITTED_CHANGES,
+            VcsError::NoCommit(_) => EXIT_NO_COMMIT,
+            VcsError::NoBranch(_) => EXIT_NO_BRANCH,
+            VcsError::MergeConflict { .. } => EXIT_MERGE_CONFLICT,
+            VcsError::MergeFromNotMasterHead => EXIT_MERGE_FROM_NOT_MASTER_HEAD,
+            VcsError::NoObject(_) => EXIT_NO_OBJECT,
+            VcsError::WrongObjectType { .. } => EXIT_WRONG_OBJECT_TYPE,
+            VcsError::NotTreeish(_) => EXIT_NOT_TREEISH,
+            VcsError::OutsideRepository(_) => EXIT_OUTSIDE_REPOSITORY,
+            VcsError::CorruptRepository { .. } => EXIT_CORRUPT_REPOSITORY,
+            VcsError::CorruptObject(_) => EXIT_CORRUPT_OBJECT,
+            VcsError::NoFile { .. } => EXIT_NO_FILE,
+            VcsError::UnknownSetting(_) => EXIT_UNKNOWN_SETTING,
+            VcsError::ReadOnlySetting(_) => EXIT_READ_ONLY_SETTING,
+            VcsError::InvalidSetting { .. } => EXIT_INVALID_SETTING,
+        }
+    }
+
+    /// Returns the variant's fields in json format.
+    pub fn fields(&self) -> Map<String, Value> {
+        let fields = match self {
+            VcsError::BranchAlreadyExists(branch) | VcsError::NoBranch(branch) => {
+                json!({ "branch": branch })
+            }
+            VcsError::UncomittedChanges { changes } => json!({ "changes": to_json(changes) }),
+            VcsError::NoCommit(id) | VcsError::NoObject(id) => json!({ "id": id }),
+            VcsError::MergeConflict { both_changed } => {
+                json!({ "both_changed": to_json(both_changed) })
+            }
+            VcsError::WrongObjectType {
+                expected,
+                found,
+                id,
+            } => json!({ "expected": expected, "found": found, "id": id }),
+            VcsError::NotTreeish(tree_ish) => json!({ "tree_ish": tree_ish }),
+            VcsError::OutsideRepository(path) | VcsError::CorruptObject(path) => {
+                json!({ "path": escape_path(path) })
+            }
+            VcsError::NoFile { path, commit } => {
+                json!({ "path": escape_path(path), "commit": commit })
+            }
+            VcsError::CorruptRepository { issues } => {
+                json!({ "issues": serde_json::to_value(issues).unwrap_or_default() })
+            }
+            VcsError::RepositoryLocked(pid) => json!({ "pid": pid }),
+            VcsError::UnknownSetting(key) | VcsError::ReadOnlySetting(key) => {
+                json!({ "key": key })
+            }
+            VcsError::InvalidSetting { key, value } => json!({ "key": key, "value": value }),
+            _ => json!({}),
+        };
+        let Value::Object(fields) = fields else {
+            unreachable!()
+        };
+        fields
+    }
+}
+
+fn to_json(changes: &FileChanges) -> Value {
+    serialize_changes(changes, serde_json::value::Serializer).unwrap_or_default()
+}
+
+/// Description of a command's failure: the name of the `VcsError` variant it
+/// was caused by (`Other` if there is none), the exit status, the message and
+/// the variant's fields.
+#[derive(Debug, Serialize)]
+pub struct ErrorInfo {
+    pub kind: &'static str,
+    pub code: i32,
+    pub message: String,
+    #[serde(flatten)]
+    pub fields: Map<String, Value>,
+}
+
+impl From<&anyhow::Error> for ErrorInfo {
+    fn from(err: &anyhow::Error) -> Self {
+        let message = err.to_string();
+        for cause in err.chain() {
+            if let Some(vcs_error) = cause.downcast_ref::<VcsError>() {
+                return Self {
+                    kind: vcs_error.kind(),
+                    code: vcs_error.exit_code(),
+                    message,
+                    fields: vcs_error.fields(),
+                };
+            }
+            if cause.is::<std::io::Error>() {
+                return Self {
+                    kind: "Io",
+                    code: EXIT_IO,
+                    message,
+                    fields: Map::new(),
+                };
+            }
+        }
+        Self {
+            kind: "Other",
+            code: EXIT_OTHER,
+            message,
+            fields: Map::new(),
+        }
+    }
 }
 
 impl From<serde_json::Error> for VcsError {
@@ -0,0 +1,38 @@
+//! Simple local version control.
+//!
+//! The [`Repository`] handle gives access to everything the `vcs` command-line
+//! tool can do and returns typed results instead of text.
+//!
+//! ```no_run
+//! use std::path::Path;
+//! use vcs::{FileStatus, Repository};
+//!
+//! let repo = Repository::open(Path::new("."))?;
+//! for (status, path) in repo.status()? {
+//!     if status == FileStatus::Modified {
+//!         println!("{}", path.display());
+//!     }
+//! }
+//! let info = repo.commit("Update files")?;
+//! println!("{}", info.human_id);
+//! # Ok::<(), anyhow::Error>(())
+//! ```
+
+mod errors;
+mod repository;
+mod vcs_manager;
+
+pub use errors::{ErrorInfo, VcsError, VcsResult};
+pub use repository::{JumpTarget, Repository};
+pub use vcs_manager::{
+    BlameLine, BranchInfo, ChangeStat, CommitLog, DateFormat, FileChanges, FileStatus, GarbageInfo,
+    HashAlgorithm, IntegrityIssue, LogFilter, NewCommitInfo, ObjectInfo, ObjectStoreKind,
+    StateInfo, TreeEntryInfo,
+};
+
+/// Helpers the `vcs` binary formats its output with, so that paths are
+/// printed the way the library serializes them. Not part of the public API.
+#[doc(hidden)]
+pub mod output {
+    pub use crate::vcs_manager::{escape_path, serialize_changes, serialize_path};
+}
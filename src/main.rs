@@ -1,42 +1,109 @@
 #[doc(hidden)]
 mod commands;
 #[doc(hidden)]
-mod errors;
-#[doc(hidden)]
 mod parser;
 #[doc(hidden)]
 mod report_printer;
-mod vcs_manager;
 
-use clap::Parser;
-use parser::{Cli, Commands};
+use clap::error::ErrorKind;
+use clap::{CommandFactory, Parser};
+use parser::{Cli, Commands, OutputFormat};
+use report_printer::{report_error_json, LogStyle};
+use vcs::{ErrorInfo, JumpTarget, LogFilter};
 
 fn main() {
     let cli = Cli::parse();
+    let format = cli.format;
     let result = match &cli.command {
-        Commands::Init { path } => commands::init::run(path),
-        Commands::Commit { message } => commands::commit::run(message),
-        Commands::Log => commands::log::run(),
-        Commands::Status => commands::status::run(),
-        Commands::Merge { branch } => commands::merge::run(branch),
-        Commands::NewBranch { name } => commands::new_branch::run(name),
+        Commands::Init {
+            path,
+            hash,
+            object_store,
+        } => commands::init::run(path, *hash, *object_store, format),
+        Commands::Commit { message } => commands::commit::run(message, format),
+        Commands::Log {
+            all,
+            graph,
+            oneline,
+            pretty,
+            stat,
+            date,
+            max_count,
+            since,
+            until,
+            grep,
+            author,
+            follow,
+            paths,
+        } => {
+            if *follow && paths.len() != 1 {
+                let mut cli = Cli::command();
+                cli.build();
+                let log = cli.find_subcommand_mut("log").unwrap();
+                log.error(
+                    ErrorKind::ArgumentConflict,
+                    "--follow requires exactly one path",
+                )
+                .exit();
+            }
+            let filter = LogFilter {
+                all: *all,
+                max_count: *max_count,
+                since: *since,
+                until: *until,
+                grep: grep.clone(),
+                author: author.clone(),
+                follow: *follow,
+                paths: Vec::new(),
+            };
+            let view = commands::log::LogView {
+                graph: *graph,
+                style: match pretty {
+                    Some(style) => style.clone(),
+                    None if *oneline => LogStyle::Oneline,
+                    None => LogStyle::Medium,
+                },
+                stat: *stat,
+                date: date.clone(),
+            };
+            commands::log::run(filter, paths, &view, format)
+        }
+        Commands::Blame { rev, path } => commands::blame::run(path, rev.as_deref(), format),
+        Commands::Status => commands::status::run(format),
+        Commands::Merge { branch } => commands::merge::run(branch, format),
+        Commands::NewBranch { name } => commands::new_branch::run(name, format),
         Commands::Jump { branch, commit } => {
             if let Some(branch_name) = branch {
-                commands::jump::to_branch(branch_name)
+                commands::jump::run(JumpTarget::Branch(branch_name), format)
             } else if let Some(id) = commit {
-                commands::jump::to_commit(id)
+                commands::jump::run(JumpTarget::Commit(id), format)
             } else {
                 unreachable!()
             }
         }
+        Commands::CatObject {
+            show_type, object, ..
+        } => commands::cat_object::run(object, *show_type, format),
+        Commands::HashObject { write, file } => commands::hash_object::run(file, *write, format),
+        Commands::LsTree {
+            recursive,
+            tree_ish,
+        } => commands::ls_tree::run(tree_ish, *recursive, format),
+        Commands::Fsck => commands::fsck::run(format),
+        Commands::Gc { prune } => commands::gc::run(*prune, format),
+        Commands::Config { key, value } => commands::config::run(key, value.as_deref(), format),
     };
     match result {
         Ok(report) => {
             print!("{report}");
         }
         Err(err) => {
-            eprintln!("{err}");
-            std::process::exit(1);
+            let info = ErrorInfo::from(&err);
+            match format {
+                OutputFormat::Text => eprintln!("{err}"),
+                OutputFormat::Json => eprint!("{}", report_error_json(&info)),
+            }
+            std::process::exit(info.code);
         }
     }
 }
@@ -1,5 +1,11 @@
-use clap::{ArgGroup, Parser, Subcommand};
+use crate::report_printer::LogStyle;
+use vcs::{DateFormat, HashAlgorithm, ObjectStoreKind};
+
+use chrono::{DateTime, Local, NaiveDate, NaiveDateTime, TimeZone};
+use clap::{ArgGroup, Parser, Subcommand, ValueEnum};
+use regex::Regex;
 use std::path::PathBuf;
+use std::time::Duration;
 
 #[derive(Parser, Debug)]
 #[command(author, version, about, long_about = None)]
@@ -7,6 +13,16 @@ use std::path::PathBuf;
 pub struct Cli {
     #[command(subcommand)]
     pub command: Commands,
+    /// Output format: text (default) or json. The json output is described in
+    /// the README.
+    #[arg(long, global = true, value_name = "format", default_value = "text")]
+    pub format: OutputFormat,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum OutputFormat {
+    Text,
+    Json,
 }
 
 #[derive(Debug, Subcommand)]
@@ -20,9 +36,19 @@ pub enum Commands {
     ///
     /// Creates a commit with "Initial commit" message including all files in
     /// the provided directory (if any).
+    ///
+    /// Objects' hashes are computed with the given algorithm: sha1 (default)
+    /// or sha256. The algorithm can't be changed later.
+    ///
+    /// Objects are stored as one file each (loose, default) or in a single
+    /// database file (database). The store can't be changed later.
     Init {
         #[arg(long, value_name = "directory_path")]
         path: PathBuf,
+        #[arg(long, value_name = "algorithm", default_value = "sha1")]
+        hash: HashAlgorithm,
+        #[arg(long, value_name = "store", default_value = "loose")]
+        object_store: ObjectStoreKind,
     },
     /// Show the working tree status
     ///
@@ -88,6 +114,227 @@ pub enum Commands {
     /// Show commit logs
     ///
     /// Shows the commit logs in reverse chronological order starting with the
-    /// current commit and ending with its branch root commit.
-    Log,
+    /// current commit and ending with the root commit. With --all, the commits
+    /// of all the branches are shown, including the ones merged by merge
+    /// commits. Commits are decorated with HEAD and the branches pointing to
+    /// them.
+    ///
+    /// Only commits matching all the options given are shown. Dates are either
+    /// YYYY-MM-DD, YYYY-MM-DD HH:MM:SS, an RFC 3339 date or an age such as 2w
+    /// (two weeks ago). With paths after --, only commits changing the paths or
+    /// files below them are shown. With --follow and a single path, the
+    /// commits changing the file under its previous names are shown too: a
+    /// file is renamed when a commit removes it and adds a file with the same
+    /// or similar contents.
+    ///
+    /// A format given with --pretty=format:<format> is printed for each commit,
+    /// with %H, %h, %an, %ad, %s, %B, %d and %D replaced by the commit's id,
+    /// short id, author, date, first line of the message, message and
+    /// references with and without parentheses. %n is a newline and %% a %.
+    Log {
+        /// Show the history of all the branches
+        #[arg(long)]
+        all: bool,
+        /// Draw the history as a graph, with a line per commit
+        #[arg(long, conflicts_with_all = ["since", "until", "grep", "author", "paths", "stat"])]
+        graph: bool,
+        /// Show each commit on a single line, like --pretty=oneline
+        #[arg(long, conflicts_with = "pretty")]
+        oneline: bool,
+        /// Print commits as medium (default), oneline or format:<format>
+        #[arg(long, value_name = "format")]
+        pretty: Option<LogStyle>,
+        /// Show the number of lines added and removed in each changed file
+        #[arg(long)]
+        stat: bool,
+        /// Print dates as default, iso, rfc, short or format:<format>, a
+        /// strftime format. Defaults to the date_format setting
+        #[arg(long, value_name = "format")]
+        date: Option<DateFormat>,
+        /// Show at most <count> commits
+        #[arg(short = 'n', value_name = "count")]
+        max_count: Option<usize>,
+        /// Show commits made at or after <date>
+        #[arg(long, value_name = "date", value_parser = parse_date)]
+        since: Option<DateTime<Local>>,
+        /// Show commits made at or before <date>
+        #[arg(long, value_name = "date", value_parser = parse_date)]
+        until: Option<DateTime<Local>>,
+        /// Show commits whose message matches <regex>
+        #[arg(long, value_name = "regex")]
+        grep: Option<Regex>,
+        /// Show commits whose author matches <regex>
+        #[arg(long, value_name = "regex")]
+        author: Option<Regex>,
+        /// Follow the file at the only path through renames
+        #[arg(long, requires = "paths")]
+        follow: bool,
+        #[arg(last = true, value_name = "path")]
+        paths: Vec<PathBuf>,
+    },
+    /// Show what commit last changed each line of a file
+    ///
+    /// Prints each line of the file after the short id of the commit that
+    /// added it or last changed it, the commit's author and date and the line
+    /// number. The file is taken from the current commit, or from the commit
+    /// or the head of the branch given with --rev.
+    Blame {
+        /// Commit id or branch name to take the file from
+        #[arg(long, value_name = "rev")]
+        rev: Option<String>,
+        #[arg(value_name = "path")]
+        path: PathBuf,
+    },
+    /// Provide content or type information for repository objects
+    ///
+    /// Prints the type of the object with the given hash (-t) or its contents
+    /// in a human-readable form (-p).
+    #[command(group(
+        ArgGroup::new("mode")
+            .required(true)
+            .args(["show_type", "pretty"])
+        ))]
+    CatObject {
+        #[arg(short = 't', conflicts_with = "pretty")]
+        show_type: bool,
+        #[arg(short = 'p')]
+        pretty: bool,
+        #[arg(value_name = "object_hash")]
+        object: String,
+    },
+    /// Compute object hash of a file
+    ///
+    /// Prints the hash of the Blob object the file would be recorded as on
+    /// commit. The file must be inside the repository. With -w the blob is
+    /// also written to the objects database.
+    HashObject {
+        #[arg(short = 'w')]
+        write: bool,
+        #[arg(value_name = "file_path")]
+        file: PathBuf,
+    },
+    /// List the contents of a tree object
+    ///
+    /// Lists the entries of the tree <tree_ish> points to. <tree_ish> is a
+    /// branch name, a commit hash or a tree hash. With -r subtrees are
+    /// expanded into the files they contain.
+    LsTree {
+        #[arg(short = 'r')]
+        recursive: bool,
+        #[arg(value_name = "tree_ish")]
+        tree_ish: String,
+    },
+    /// Verify the integrity of the repository
+    ///
+    /// Checks that every object's contents match the hash in its path, that
+    /// every commit's tree and parent and every tree entry point to existing
+    /// objects of the right type and that branch heads and STATE point to
+    /// existing commits. Reports dangling and unreachable objects.
+    ///
+    /// Fails if the repository is corrupted.
+    Fsck,
+    /// Remove unreachable objects
+    ///
+    /// Deletes the objects that can't be reached from branch heads and STATE
+    /// and are older than <age>. <age> is either "now" or a number followed
+    /// by one of the units s, m, h, d, w (e.g. 30m or 2w). Reports the number
+    /// of removed objects and the disk space freed.
+    Gc {
+        #[arg(long, value_name = "age", default_value = "2w", value_parser = parse_age)]
+        prune: Duration,
+    },
+    /// Get and set repository settings
+    ///
+    /// Prints the value of the setting <key> or sets it to <value> if one is
+    /// provided. Settings are stored in .vcs/config.json.
+    ///
+    /// Available settings:
+    ///   hash            algorithm used to compute objects' hashes, set on init
+    ///                   (read-only)
+    ///   object_store    where objects are stored, loose or database, set on
+    ///                   init (read-only)
+    ///   verify_objects  check objects' contents against their hashes on every
+    ///                   read (default: true)
+    ///   track_empty_dirs
+    ///                   record new empty directories in commits (default: false)
+    ///   chunk_threshold store files of at least this many bytes as chunks, 0
+    ///                   disables chunking (default: 8388608)
+    ///   author          name recorded as the author of new commits (default:
+    ///                   the name of the user running vcs)
+    #[command(verbatim_doc_comment)]
+    Config { key: String, value: Option<String> },
+}
+
+/// Parses an age of the form "now" or <number><unit>, where unit is one of s,
+/// m, h, d, w.
+fn parse_age(age: &str) -> Result<Duration, String> {
+    if age == "now" {
+        return Ok(Duration::ZERO);
+    }
+    let error = || format!("invalid age {age:?}, expected \"now\" or e.g. 30m, 12h, 2w");
+    let unit_start = age.find(|c: char| !c.is_ascii_digit()).ok_or_else(error)?;
+    let (number, unit) = age.split_at(unit_start);
+    let number: u64 = number.parse().map_err(|_| error())?;
+    let seconds = match unit {
+        "s" => 1,
+        "m" => 60,
+        "h" => 60 * 60,
+        "d" => 24 * 60 * 60,
+        "w" => 7 * 24 * 60 * 60,
+        _ => return Err(error()),
+    };
+    let seconds = number.checked_mul(seconds).ok_or_else(error)?;
+    Ok(Duration::from_secs(seconds))
+}
+
+/// Parses a date given as YYYY-MM-DD (midnight in the local time zone),
+/// YYYY-MM-DD HH:MM:SS (in the local time zone), an RFC 3339 date or an age as
+/// accepted by `parse_age`, meaning that long ago.
+fn parse_date(date: &str) -> Result<DateTime<Local>, String> {
+    let error = || format!("invalid date {date:?}, expected e.g. 2024-01-31 or 2w");
+    if let Ok(age) = parse_age(date) {
+        return chrono::Duration::from_std(age)
+            .ok()
+            .and_then(|age| Local::now().checked_sub_signed(age))
+            .ok_or_else(error);
+    }
+    if let Ok(date) = DateTime::parse_from_rfc3339(date) {
+        return Ok(date.with_timezone(&Local));
+    }
+    let local = NaiveDateTime::parse_from_str(date, "%Y-%m-%d %H:%M:%S")
+        .or_else(|_| NaiveDate::parse_from_str(date, "%Y-%m-%d").map(|day| day.into()))
+        .map_err(|_| error())?;
+    Local
+        .from_local_datetime(&local)
+        .earliest()
+        .ok_or_else(|| format!("date {date:?} doesn't exist in the local time zone"))
+}
+
+#[test]
+fn test_parse_date() {
+    let midnight = Local.with_ymd_and_hms(2024, 1, 31, 0, 0, 0).unwrap();
+    assert_eq!(parse_date("2024-01-31"), Ok(midnight));
+    assert_eq!(
+        parse_date("2024-01-31 12:30:00"),
+        Ok(midnight + chrono::Duration::minutes(12 * 60 + 30))
+    );
+    assert_eq!(
+        parse_date("2024-01-31T00:00:00Z").unwrap(),
+        chrono::Utc.with_ymd_and_hms(2024, 1, 31, 0, 0, 0).unwrap()
+    );
+    let week_ago = parse_date("1w").unwrap();
+    assert!(Local::now() - week_ago >= chrono::Duration::weeks(1));
+    assert!(parse_date("yesterday").is_err());
+    assert!(parse_date("99999999w").is_err());
+}
+
+#[test]
+fn test_parse_age() {
+    assert_eq!(parse_age("now"), Ok(Duration::ZERO));
+    assert_eq!(parse_age("30m"), Ok(Duration::from_secs(30 * 60)));
+    assert_eq!(parse_age("2w"), Ok(Duration::from_secs(14 * 24 * 60 * 60)));
+    assert!(parse_age("2").is_err());
+    assert!(parse_age("w").is_err());
+    assert!(parse_age("2 weeks").is_err());
+    assert!(parse_age("9999999999999999w").is_err());
 }
@@ -1,5 +1,47 @@
-use crate::vcs_manager::{CommitLog, FileStatus, NewCommitInfo, StateInfo};
-use std::path::PathBuf;
+use serde::Serialize;
+use std::collections::HashSet;
+use std::path::{Path, PathBuf};
+use vcs::output::{escape_path, serialize_changes, serialize_path};
+use vcs::{
+    BlameLine, ChangeStat, CommitLog, DateFormat, ErrorInfo, FileChanges, FileStatus, GarbageInfo,
+    IntegrityIssue, NewCommitInfo, ObjectInfo, StateInfo, TreeEntryInfo, VcsResult,
+};
+
+const SHORT_ID_LEN: usize = 7;
+/// Longest bar of `+` and `-` drawn for a file in a stat.
+const STAT_BAR_WIDTH: usize = 40;
+
+/// Formats the value as a single line of json.
+pub fn report_json(value: &impl Serialize) -> VcsResult<String> {
+    Ok(serde_json::to_string(value)? + "\n")
+}
+
+#[derive(Serialize)]
+struct Status<'a> {
+    branch: &'a str,
+    #[serde(serialize_with = "serialize_changes")]
+    changes: &'a [(FileStatus, PathBuf)],
+}
+
+pub fn report_status_json(branch: &str, changes: &FileChanges) -> VcsResult<String> {
+    report_json(&Status { branch, changes })
+}
+
+#[derive(Serialize)]
+struct Init<'a> {
+    #[serde(serialize_with = "serialize_path")]
+    root: &'a Path,
+    commit: &'a NewCommitInfo,
+}
+
+pub fn report_init_json(root: &Path, commit: &NewCommitInfo) -> VcsResult<String> {
+    report_json(&Init { root, commit })
+}
+
+/// Formats the error as a single line of json.
+pub fn report_error_json(info: &ErrorInfo) -> String {
+    serde_json::json!({ "error": info }).to_string() + "\n"
+}
 
 pub fn report_current_branch(branch_name: &str) -> String {
     format!("On branch {branch_name}\n")
@@ -13,8 +55,8 @@ pub fn report_creating_new_branch(info: &StateInfo) -> String {
     report + "\n"
 }
 
-pub fn report_successful_init(path: &str) -> String {
-    format!("Initialized VCS repository in {path}\n")
+pub fn report_successful_init(path: &Path) -> String {
+    format!("Initialized VCS repository in {}\n", escape_path(path))
 }
 
 pub fn report_successful_commit(info: &NewCommitInfo) -> String {
@@ -25,12 +67,7 @@ pub fn report_successful_commit(info: &NewCommitInfo) -> String {
 pub fn report_changes(changes: &[(FileStatus, PathBuf)]) -> String {
     let mut report = String::new();
     for (status, path) in changes.iter() {
-        report += match status {
-            FileStatus::Added => "  added: ",
-            FileStatus::Modified => "  modified: ",
-            FileStatus::Unchanged => unreachable!(),
-        };
-        report += &format!("{}\n", path.to_str().unwrap());
+        report += &format!("  {status}: {}\n", escape_path(path));
     }
     report
 }
@@ -51,18 +88,155 @@ pub fn report_successful_jump_to_branch(info: &StateInfo) -> String {
     report + "\n"
 }
 
-pub fn display_logs(logs: &[CommitLog]) -> String {
+/// Returns the beginning of the public id, which is enough to tell commits
+/// apart in most repositories.
+pub fn short_id(human_id: &str) -> &str {
+    &human_id[..SHORT_ID_LEN.min(human_id.len())]
+}
+
+/// Returns the references pointing to the commit separated by commas, like
+/// `HEAD, master`.
+fn format_references(commit: &CommitLog) -> String {
+    let mut references = Vec::new();
+    if commit.head {
+        references.push("HEAD");
+    }
+    references.extend(commit.branches.iter().map(String::as_str));
+    references.join(", ")
+}
+
+/// Formats the references pointing to the commit as ` (HEAD, master)`, or
+/// returns an empty string if there are none.
+fn format_decorations(commit: &CommitLog) -> String {
+    let references = format_references(commit);
+    if references.is_empty() {
+        return references;
+    }
+    format!(" ({references})")
+}
+
+/// How `log` prints each commit.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub enum LogStyle {
+    /// The commit's id, author, date, message and changes on several lines.
+    #[default]
+    Medium,
+    /// The short id, the references and the first line of the message.
+    Oneline,
+    /// A line where placeholders are replaced by the commit's fields:
+    ///
+    /// | Placeholder | Replaced by                           |
+    /// |-------------|---------------------------------------|
+    /// | `%H`        | id                                    |
+    /// | `%h`        | short id                              |
+    /// | `%an`       | author                                |
+    /// | `%ad`       | date                                  |
+    /// | `%s`        | first line of the message             |
+    /// | `%B`        | message                               |
+    /// | `%d`        | references, like ` (HEAD, master)`    |
+    /// | `%D`        | references without the parentheses    |
+    /// | `%n`        | newline                               |
+    /// | `%%`        | `%`                                   |
+    ///
+    /// Other placeholders are left as they are.
+    Format(String),
+}
+
+impl std::str::FromStr for LogStyle {
+    type Err = String;
+
+    fn from_str(name: &str) -> Result<Self, Self::Err> {
+        match name {
+            "medium" => Ok(LogStyle::Medium),
+            "oneline" => Ok(LogStyle::Oneline),
+            _ => match name.strip_prefix("format:") {
+                Some(format) => Ok(LogStyle::Format(format.to_owned())),
+                None => Err(format!(
+                    "unknown log format {name:?}, expected medium, oneline or format:<format>"
+                )),
+            },
+        }
+    }
+}
+
+/// Replaces the placeholders in the format, as described in [LogStyle].
+fn format_commit(format: &str, commit: &CommitLog, date_format: &DateFormat) -> String {
+    let mut report = String::new();
+    let mut rest = format;
+    while let Some(start) = rest.find('%') {
+        report += &rest[..start];
+        rest = &rest[start..];
+        let (replacement, len) = if rest.starts_with("%an") {
+            (commit.author.clone(), 3)
+        } else if rest.starts_with("%ad") {
+            (date_format.format_time(&commit.time), 3)
+        } else {
+            let replacement = match rest[1..].chars().next() {
+                Some('H') => commit.human_id.clone(),
+                Some('h') => short_id(&commit.human_id).to_owned(),
+                Some('s') => first_line(&commit.message).to_owned(),
+                Some('B') => commit.message.clone(),
+                Some('d') => format_decorations(commit),
+                Some('D') => format_references(commit),
+                Some('n') => "\n".to_owned(),
+                Some('%') => "%".to_owned(),
+                _ => {
+                    report += "%";
+                    rest = &rest[1..];
+                    continue;
+                }
+            };
+            (replacement, 2)
+        };
+        report += &replacement;
+        rest = &rest[len..];
+    }
+    report + rest
+}
+
+fn first_line(message: &str) -> &str {
+    message.lines().next().unwrap_or_default()
+}
+
+/// Formats the commit on a single line, for the one line styles.
+fn format_line(commit: &CommitLog, style: &LogStyle, date_format: &DateFormat) -> String {
+    match style {
+        LogStyle::Format(format) => format_commit(format, commit, date_format),
+        LogStyle::Medium | LogStyle::Oneline => format!(
+            "{}{} {}",
+            short_id(&commit.human_id),
+            format_decorations(commit),
+            first_line(&commit.message)
+        ),
+    }
+}
+
+pub fn display_logs(logs: &[CommitLog], style: &LogStyle, date_format: &DateFormat) -> String {
     let mut iter = logs.iter().peekable();
     let mut report = String::new();
     while let Some(commit) = iter.next() {
+        if *style != LogStyle::Medium {
+            report += &format_line(commit, style, date_format);
+            report += "\n";
+            if let Some(stat) = &commit.stat {
+                report += &report_stat(stat);
+            }
+            continue;
+        }
+        report += &format!("commit {}{}\n", commit.human_id, format_decorations(commit));
+        if !commit.author.is_empty() {
+            report += &format!("Author: {}\n", commit.author);
+        }
         report += &format!(
-            "commit {}\nDate: {}\nMessage: {}\n",
-            commit.human_id,
-            commit.time.format("%a %b %e %H:%M:%S %Y %z"),
+            "Date: {}\nMessage: {}\n",
+            date_format.format_time(&commit.time),
             commit.message
         );
         if commit.changes.is_empty() {
             report += "No changes\n";
+        } else if let Some(stat) = &commit.stat {
+            report += "Changes:\n";
+            report += &report_stat(stat);
         } else {
             report += "Changes:\n";
             report += &report_changes(&commit.changes);
@@ -73,3 +247,265 @@ pub fn display_logs(logs: &[CommitLog]) -> String {
     }
     report
 }
+
+/// Lists the line counts of the changed files with bars of `+` and `-`,
+/// followed by the totals. Binary files are marked as `Bin`.
+pub fn report_stat(stats: &[ChangeStat]) -> String {
+    let paths: Vec<_> = stats.iter().map(|stat| escape_path(&stat.path)).collect();
+    let path_width = paths.iter().map(|path| path.chars().count()).max();
+    let totals: Vec<_> = stats
+        .iter()
+        .map(|stat| stat.added.unwrap_or(0) + stat.removed.unwrap_or(0))
+        .collect();
+    let max_total = totals.iter().copied().max().unwrap_or(0);
+    let count_width = max_total.to_string().len();
+    // Long bars are scaled down, keeping at least a sign for every change.
+    let scale = |count: usize| match count {
+        0 => 0,
+        _ if max_total <= STAT_BAR_WIDTH => count,
+        _ => (count * STAT_BAR_WIDTH / max_total).max(1),
+    };
+    let mut report = String::new();
+    for ((stat, path), total) in stats.iter().zip(&paths).zip(&totals) {
+        let path_width = path_width.unwrap_or(0);
+        match (stat.added, stat.removed) {
+            (Some(added), Some(removed)) => {
+                report += &format!(
+                    " {path:<path_width$} | {total:>count_width$} {}{}\n",
+                    "+".repeat(scale(added)),
+                    "-".repeat(scale(removed))
+                )
+            }
+            _ => report += &format!(" {path:<path_width$} | Bin\n"),
+        }
+    }
+    let added: usize = stats.iter().filter_map(|stat| stat.added).sum();
+    let removed: usize = stats.iter().filter_map(|stat| stat.removed).sum();
+    let plural = |count: usize| if count == 1 { "" } else { "s" };
+    report += &format!(" {} file{} changed", stats.len(), plural(stats.len()));
+    if added > 0 {
+        report += &format!(", {added} insertion{}(+)", plural(added));
+    }
+    if removed > 0 {
+        report += &format!(", {removed} deletion{}(-)", plural(removed));
+    }
+    report + "\n"
+}
+
+/// Draws the history as a graph with a line per commit. Each commit is joined
+/// to its parents by lanes; a lane forks at a merge commit and lanes join where
+/// branches were created. The logs must list children before their parents.
+/// Parents that are not listed are left out. Commits are printed in the one
+/// line style unless a format is given.
+pub fn display_graph(logs: &[CommitLog], style: &LogStyle, date_format: &DateFormat) -> String {
+    let listed: HashSet<_> = logs.iter().map(|commit| commit.human_id.as_str()).collect();
+    // The commits each lane is waiting for. Lanes that ended are None, so that
+    // the other lanes keep their columns.
+    let mut lanes: Vec<Option<&str>> = Vec::new();
+    let mut report = String::new();
+    for commit in logs {
+        let id = commit.human_id.as_str();
+        let column = match lanes.iter().position(|lane| *lane == Some(id)) {
+            Some(column) => column,
+            None => take_free_lane(&mut lanes, 0),
+        };
+        // Lanes waiting for the same commit join it before its line.
+        let joined: Vec<_> = (column + 1..lanes.len())
+            .filter(|&lane| lanes[lane] == Some(id))
+            .collect();
+        if !joined.is_empty() {
+            for &lane in &joined {
+                lanes[lane] = None;
+            }
+            let mut row = draw_lanes(&lanes);
+            // Nearer edges are drawn last, so that their diagonals are kept.
+            for &lane in joined.iter().rev() {
+                draw_edge(&mut row, lane, column);
+            }
+            report += String::from_iter(row).trim_end();
+            report += "\n";
+        }
+
+        let mut row = draw_lanes(&lanes);
+        row[2 * column] = '*';
+        report += &format!(
+            "{} {}\n",
+            String::from_iter(row).trim_end(),
+            format_line(commit, style, date_format)
+        );
+
+        let mut parents = commit
+            .parents
+            .iter()
+            .map(String::as_str)
+            .filter(|parent| listed.contains(parent));
+        lanes[column] = parents.next();
+        let mut forks = Vec::new();
+        for parent in parents {
+            let lane = match lanes.iter().position(|lane| *lane == Some(parent)) {
+                Some(lane) => lane,
+                None => {
+                    let lane = take_free_lane(&mut lanes, column + 1);
+                    lanes[lane] = Some(parent);
+                    // The new lane starts on the next row.
+                    forks.push((lane, true));
+                    continue;
+                }
+            };
+            forks.push((lane, false));
+        }
+
+        if !forks.is_empty() {
+            let mut row = draw_lanes(&lanes);
+            for &(lane, is_new) in &forks {
+                if is_new {
+                    row[2 * lane] = ' ';
+                }
+                draw_edge(&mut row, column, lane);
+            }
+            report += String::from_iter(row).trim_end();
+            report += "\n";
+        }
+        while lanes.last() == Some(&None) {
+            lanes.pop();
+        }
+    }
+    report
+}
+
+/// Returns the first lane from `start` on that ended, adding a lane if there is
+/// none.
+fn take_free_lane(lanes: &mut Vec<Option<&str>>, start: usize) -> usize {
+    match (start..lanes.len()).find(|&lane| lanes[lane].is_none()) {
+        Some(lane) => lane,
+        None => {
+            lanes.push(None);
+            lanes.len() - 1
+        }
+    }
+}
+
+/// Draws a `|` for every lane still waiting for a commit. Lanes take every
+/// other character, edges between lanes are drawn in between.
+fn draw_lanes(lanes: &[Option<&str>]) -> Vec<char> {
+    let mut row = vec![' '; (2 * lanes.len()).max(1)];
+    for (lane, commit) in lanes.iter().enumerate() {
+        if commit.is_some() {
+            row[2 * lane] = '|';
+        }
+    }
+    row
+}
+
+/// Draws the edge from lane `from` down to lane `to`: a diagonal next to
+/// `from`, continued by a horizontal line if the lanes are not adjacent.
+fn draw_edge(row: &mut [char], from: usize, to: usize) {
+    let (diagonal, line) = if to > from {
+        ((2 * from + 1, '\\'), 2 * from + 2..2 * to)
+    } else if to < from {
+        ((2 * from - 1, '/'), 2 * to + 1..2 * from - 1)
+    } else {
+        return;
+    };
+    // The line passes between the lanes and through the ended ones.
+    for position in line {
+        if row[position] == ' ' {
+            row[position] = '_';
+        }
+    }
+    row[diagonal.0] = diagonal.1;
+}
+
+/// Prints each line of the file after the short id of the commit it comes
+/// from, the commit's author and date and the line number.
+pub fn display_blame(blame: &[BlameLine], date_format: &DateFormat) -> String {
+    let author_width = blame.iter().map(|line| line.author.chars().count()).max();
+    let number_width = blame.len().to_string().len();
+    let mut report = String::new();
+    for line in blame {
+        report += &format!(
+            "{} ({:<author_width$} {} {:>number_width$}) {}\n",
+            short_id(&line.human_id),
+            line.author,
+            date_format.format_time(&line.time),
+            line.line,
+            line.content,
+            author_width = author_width.unwrap_or(0),
+        );
+    }
+    report
+}
+
+pub fn report_object_type(info: &ObjectInfo) -> String {
+    format!("{}\n", info.kind())
+}
+
+pub fn report_object(info: &ObjectInfo, date_format: &DateFormat) -> String {
+    match info {
+        ObjectInfo::Commit {
+            tree,
+            parent,
+            branch,
+            message,
+            time,
+            author,
+            merged,
+        } => {
+            let mut report = format!("tree {tree}\n");
+            for parent in parent.iter().chain(merged) {
+                report += &format!("parent {parent}\n");
+            }
+            report += &format!("branch {branch}\n");
+            if !author.is_empty() {
+                report += &format!("author {author}\n");
+            }
+            report += &format!("date {}\n", date_format.format_time(time));
+            report + &format!("\n{message}\n")
+        }
+        ObjectInfo::Blob(data) | ObjectInfo::Chunk(data) => {
+            String::from_utf8_lossy(data).into_owned()
+        }
+        ObjectInfo::Tree(entries) => report_tree_entries(entries),
+        ObjectInfo::ChunkList(chunks) => chunks
+            .iter()
+            .map(|human_id| format!("chunk {human_id}\n"))
+            .collect(),
+    }
+}
+
+pub fn report_tree_entries(entries: &[TreeEntryInfo]) -> String {
+    let mut report = String::new();
+    for entry in entries.iter() {
+        report += &format!(
+            "{} {}\t{}\n",
+            entry.kind,
+            entry.human_id,
+            escape_path(&entry.path)
+        );
+    }
+    report
+}
+
+pub fn report_object_id(human_id: &str) -> String {
+    format!("{human_id}\n")
+}
+
+pub fn report_integrity_issues(issues: &[IntegrityIssue]) -> String {
+    let mut report = String::new();
+    for issue in issues.iter() {
+        report += &format!("{issue}\n");
+    }
+    report
+}
+
+pub fn report_garbage_collection(info: &GarbageInfo) -> String {
+    let mut report = String::new();
+    for path in info.corrupt_objects.iter() {
+        report += &format!("skipped corrupt object: {}\n", escape_path(path));
+    }
+    report += &format!(
+        "Removed {} unreachable object(s), freed {} bytes\n",
+        info.removed_objects, info.freed_bytes
+    );
+    report
+}
@@ -0,0 +1,171 @@
+use crate::errors::VcsResult;
+use crate::vcs_manager::{
+    self, BlameLine, BranchInfo, CommitLog, DateFormat, FileChanges, GarbageInfo, HashAlgorithm,
+    IntegrityIssue, LogFilter, NewCommitInfo, ObjectInfo, ObjectStoreKind, StateInfo,
+    TreeEntryInfo, VcsRepository,
+};
+
+use std::path::Path;
+use std::time::Duration;
+
+/// Handle to a repository. The settings are loaded when the repository is
+/// opened and the commits and trees read are cached for the handle's lifetime.
+/// The object store is only kept open during a call.
+/// Each update locks the repository, loads its metadata once and writes it
+/// back at once, so several handles to the same repository can be used at the
+/// same time.
+#[derive(Debug)]
+pub struct Repository {
+    inner: VcsRepository,
+}
+
+/// What to jump to: the head of a branch or a commit given by its public id.
+#[derive(Debug, Clone, Copy)]
+pub enum JumpTarget<'a> {
+    Branch(&'a str),
+    Commit(&'a str),
+}
+
+impl Repository {
+    /// Opens the repository containing the directory. If the previous command
+    /// was interrupted in the middle of a metadata update, completes the
+    /// update.
+    pub fn open(directory: &Path) -> VcsResult<Self> {
+        let directory = directory.canonicalize()?;
+        Ok(Self {
+            inner: vcs_manager::open_repository(&directory)?,
+        })
+    }
+
+    /// Creates a repository in the directory with all its files recorded in
+    /// the initial commit. Objects' ids are computed with the given hash
+    /// algorithm and objects are kept in the given kind of store.
+    pub fn init(
+        directory: &Path,
+        hash: HashAlgorithm,
+        object_store: ObjectStoreKind,
+    ) -> VcsResult<(Self, NewCommitInfo)> {
+        let root = directory.canonicalize()?;
+        vcs_manager::init_vcs_directory(&root, hash, object_store)?;
+        let repository = Self {
+            inner: VcsRepository::open(root)?,
+        };
+        let info = repository.commit("Initial commit")?;
+        Ok((repository, info))
+    }
+
+    /// Returns the repository's root directory.
+    pub fn root(&self) -> &Path {
+        &self.inner
+    }
+
+    /// Returns the current branch and commit.
+    pub fn state(&self) -> VcsResult<StateInfo> {
+        vcs_manager::get_state(&self.inner)
+    }
+
+    /// Returns the files changed since the current commit, with paths relative
+    /// to the repository root.
+    pub fn status(&self) -> VcsResult<FileChanges> {
+        self.run(vcs_manager::get_changes)
+    }
+
+    /// Records all the changes in a new commit on the current branch.
+    pub fn commit(&self, message: &str) -> VcsResult<NewCommitInfo> {
+        self.run(|repo| vcs_manager::make_commit(repo, message))
+    }
+
+    /// Returns the logs of the commits from the current one to the root
+    /// commit that the filter accepts.
+    pub fn log(&self, filter: &LogFilter) -> VcsResult<Vec<CommitLog>> {
+        self.run(|repo| vcs_manager::get_commit_logs(repo, filter))
+    }
+
+    /// Counts the lines each logged commit added and removed in each file it
+    /// changed and stores the counts in the log's `stat`.
+    pub fn add_stats(&self, logs: &mut [CommitLog]) -> VcsResult<()> {
+        self.run(|repo| vcs_manager::add_commit_stats(repo, logs))
+    }
+
+    /// Returns how dates are printed.
+    pub fn date_format(&self) -> DateFormat {
+        self.inner.config().date_format.clone()
+    }
+
+    /// Attributes each line of the file to the commit that added it or last
+    /// changed it. The file is taken from the revision, a commit id or a
+    /// branch name, or from the current commit. Its path is relative to the
+    /// repository root.
+    pub fn blame(&self, path: &Path, rev: Option<&str>) -> VcsResult<Vec<BlameLine>> {
+        self.run(|repo| vcs_manager::blame_file(repo, path, rev))
+    }
+
+    /// Updates the working tree to the target. Fails if there are uncommitted
+    /// changes.
+    pub fn jump(&self, target: JumpTarget) -> VcsResult<StateInfo> {
+        self.run(|repo| match target {
+            JumpTarget::Branch(name) => vcs_manager::jump_to_branch(repo, name),
+            JumpTarget::Commit(id) => vcs_manager::jump_to_commit(repo, id),
+        })
+    }
+
+    /// Merges the branch into master with a merge commit. The branch is kept.
+    pub fn merge(&self, branch: &str) -> VcsResult<NewCommitInfo> {
+        self.run(|repo| vcs_manager::merge_branch(repo, branch))
+    }
+
+    /// Lists the branches in alphabetical order.
+    pub fn branches(&self) -> VcsResult<Vec<BranchInfo>> {
+        vcs_manager::get_branches(&self.inner)
+    }
+
+    /// Creates a branch at the current commit and switches to it.
+    pub fn create_branch(&self, name: &str) -> VcsResult<StateInfo> {
+        self.run(|repo| vcs_manager::create_branch(repo, name))
+    }
+
+    /// Reads the object with the given public id.
+    pub fn object(&self, id: &str) -> VcsResult<ObjectInfo> {
+        self.run(|repo| vcs_manager::get_object(repo, id))
+    }
+
+    /// Returns the public id of the object the file would be recorded as. The
+    /// object is also written to the objects database iff `write` is set.
+    pub fn hash_file(&self, file: &Path, write: bool) -> VcsResult<String> {
+        let file = file.canonicalize()?;
+        self.run(|repo| vcs_manager::hash_file(repo, &file, write))
+    }
+
+    /// Lists the tree a branch name, a commit id or a tree id points to.
+    /// Subtrees are expanded iff `recursive` is set.
+    pub fn list_tree(&self, tree_ish: &str, recursive: bool) -> VcsResult<Vec<TreeEntryInfo>> {
+        self.run(|repo| vcs_manager::list_tree(repo, tree_ish, recursive))
+    }
+
+    /// Checks the integrity of the objects database and the references.
+    pub fn check(&self) -> VcsResult<Vec<IntegrityIssue>> {
+        self.run(vcs_manager::check_repository)
+    }
+
+    /// Removes the unreachable objects older than the grace period.
+    pub fn collect_garbage(&self, grace_period: Duration) -> VcsResult<GarbageInfo> {
+        self.run(|repo| vcs_manager::collect_garbage(repo, grace_period))
+    }
+
+    /// Returns the value of the setting.
+    pub fn setting(&self, key: &str) -> VcsResult<serde_json::Value> {
+        vcs_manager::get_setting(&self.inner, key)
+    }
+
+    /// Updates the setting.
+    pub fn set_setting(&mut self, key: &str, value: &str) -> VcsResult<()> {
+        vcs_manager::set_setting(&mut self.inner, key, value)
+    }
+
+    /// Runs the operation with the object store kept open, so that the store
+    /// is opened once per call and not between calls.
+    fn run<T>(&self, operation: impl FnOnce(&VcsRepository) -> VcsResult<T>) -> VcsResult<T> {
+        let _session = self.inner.store().session()?;
+        operation(&self.inner)
+    }
+}
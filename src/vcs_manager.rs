@@ -1,32 +1,51 @@
+mod blob_stream;
 mod file_manager;
+mod line_diff;
+mod lock;
+mod object_store;
 mod objects;
 mod objects_manager;
 pub mod public_info;
+mod raw_path;
+mod repository;
 mod traits;
+mod transaction;
 
+use self::lock::RepositoryLock;
 use self::objects::*;
+pub use self::objects::{DateFormat, HashAlgorithm, ObjectStoreKind};
 use self::objects_manager::*;
 pub use self::public_info::*;
+pub use self::repository::VcsRepository;
 use self::traits::VcsSerialize;
+use self::transaction::Transaction;
 use crate::errors::{VcsError, VcsResult};
 
 use array_tool::vec::Intersect;
 use chrono::DateTime;
+use std::collections::{BTreeMap, BTreeSet, BinaryHeap};
 use std::fs;
 use std::path::{Path, PathBuf};
-use std::time::SystemTime;
+use std::time::{Duration, SystemTime};
 
 /// Creates **.vcs** directory together with all its subdirectories and files to
-/// match the following structure:
+/// match the following structure. Objects' ids in the repository are computed
+/// with the given hash algorithm and objects are kept in the given kind of
+/// store: the objects directory below, or a single objects.db file.
 ///
 /// .vcs
+/// ├── config
 /// ├── index
 /// ├── STATE
 /// ├── objects
 /// │     └── ...
 /// └── refs
 ///     └── heads
-pub fn init_vcs_directory(path: &Path) -> VcsResult<()> {
+pub fn init_vcs_directory(
+    path: &Path,
+    hash: HashAlgorithm,
+    object_store: ObjectStoreKind,
+) -> VcsResult<()> {
     assert!(path.is_absolute());
     let vcs_directory = get_vcs_root(path);
     if vcs_directory.is_dir() {
@@ -36,6 +55,7 @@ pub fn init_vcs_directory(path: &Path) -> VcsResult<()> {
     init_state(path)?;
     init_index(path)?;
     init_heads(path)?;
+    init_config(path, hash, object_store)?;
     Ok(())
 }
 
@@ -50,36 +70,53 @@ pub fn find_repository(current_dir: &Path) -> VcsResult<PathBuf> {
     Ok(Err(VcsError::NotVcsRepository)?)
 }
 
+/// Finds a repository root like `find_repository` does and opens the
+/// repository. If the previous command was interrupted in the middle of a
+/// metadata update, completes the update.
+pub fn open_repository(current_dir: &Path) -> VcsResult<VcsRepository> {
+    let repo = VcsRepository::open(find_repository(current_dir)?)?;
+    recover_update(&repo)?;
+    Ok(repo)
+}
+
 /// Get all the files that have been changed since the current commit. If
 /// succeeds, returns `FileChanges` object, which is basically a
 /// `Vec<(FileStatus, PathBuf)>`. The paths are returned
 /// as relative to the repository root.
-pub fn get_changes(repo: &Path) -> VcsResult<FileChanges> {
-    let index = Index::load(&get_vcs_index_path(repo))?;
-    Ok(into_pathspec(repo, get_changed_files(repo, &index)?))
+pub fn get_changes(repo: &VcsRepository) -> VcsResult<FileChanges> {
+    // The repository is not locked while looking for changes. The stat data
+    // refreshed meanwhile is only saved if the lock is free and the index
+    // hasn't been replaced since it was loaded, otherwise it is simply dropped,
+    // so that a concurrent update is neither blocked nor overwritten.
+    let index_path = get_vcs_index_path(repo);
+    let loaded_stat = file_manager::get_file_stat(&index_path)?;
+    let mut index = Index::load(&index_path)?;
+    let changes = get_changed_files(repo, &mut index)?;
+    if index.is_modified() {
+        if let Ok(_lock) = RepositoryLock::acquire(repo) {
+            if file_manager::get_file_stat(&index_path)? == loaded_stat {
+                index.save(&index_path)?;
+            }
+        }
+    }
+    Ok(into_pathspec(repo, changes))
 }
 
 // Get information about the repository STATE
-pub fn get_state(repo: &Path) -> VcsResult<StateInfo> {
+pub fn get_state(repo: &VcsRepository) -> VcsResult<StateInfo> {
     let state = VcsRepositoryState::load(&get_vcs_state_path(repo))?;
     Ok(StateInfo::from(state))
 }
 
 /// Forms a new commit from all the current changes in the repository. Updates
 /// STATE so that it points to the newly created commit.
-pub fn make_commit(repo: &Path, message: &str) -> VcsResult<NewCommitInfo> {
-    let index_path = get_vcs_index_path(repo);
-    let mut index = Index::load(&index_path)?;
-    let changed_files = get_changed_files(repo, &index)?;
-
-    let state_path = get_vcs_state_path(repo);
-    let mut state = VcsRepositoryState::load(&state_path)?;
-
-    let heads_path = get_vcs_heads_path(repo);
-    let heads = RefStorage::load(&heads_path)?;
+pub fn make_commit(repo: &VcsRepository, message: &str) -> VcsResult<NewCommitInfo> {
+    let mut transaction = Transaction::begin(repo)?;
+    let changed_files = get_changed_files(repo, &mut transaction.index)?;
 
+    let state = &transaction.state;
     if let Some(current_commit) = &state.current_commit {
-        let branch_head = heads.get_id(&state.current_branch);
+        let branch_head = transaction.heads.get_id(&state.current_branch);
         if branch_head != current_commit {
             Err(VcsError::CommitFromNonHead)?;
         }
@@ -88,11 +125,8 @@ pub fn make_commit(repo: &Path, message: &str) -> VcsResult<NewCommitInfo> {
         }
     };
 
-    for (_status, path) in changed_files.iter() {
-        add_blob(repo, path, &mut index)?;
-    }
-    let snapshot = build_tree(repo, repo, &index)?;
-    index.save(&index_path)?;
+    stage_changes(repo, &changed_files, &mut transaction.index)?;
+    let snapshot = build_tree(repo, repo, &transaction.index)?;
 
     let commit = Commit {
         tree: snapshot,
@@ -100,15 +134,13 @@ pub fn make_commit(repo: &Path, message: &str) -> VcsResult<NewCommitInfo> {
         branch: state.current_branch.clone(),
         message: message.to_string(),
         time: SystemTime::now(),
+        author: get_author(repo),
+        merged: Vec::new(),
     };
     let commit_id = record_commit(repo, &commit)?;
-    state.current_commit = Some(commit_id);
-
-    let mut heads = heads;
-    heads.update(state.current_branch.clone(), commit_id);
-
-    state.save(&state_path)?;
-    heads.save(&heads_path)?;
+    transaction.state.current_commit = Some(commit_id);
+    transaction.heads.update(commit.branch.clone(), commit_id);
+    transaction.commit(None)?;
 
     Ok(NewCommitInfo {
         human_id: get_human_id(&commit_id),
@@ -120,177 +152,363 @@ pub fn make_commit(repo: &Path, message: &str) -> VcsResult<NewCommitInfo> {
 
 /// Creates new branch with a name `branch_name` unless it already exists.
 /// Checks that STATE is at the MASTER's head.
-pub fn create_branch(repo: &Path, branch_name: &str) -> VcsResult<StateInfo> {
-    let state_path = get_vcs_state_path(repo);
-    let mut state = VcsRepositoryState::load(&state_path)?;
-    if state.current_branch != MASTER_BRANCH {
+pub fn create_branch(repo: &VcsRepository, branch_name: &str) -> VcsResult<StateInfo> {
+    let mut transaction = Transaction::begin(repo)?;
+    if transaction.state.current_branch != MASTER_BRANCH {
         Err(VcsError::BranchOffNonMaster)?;
     }
-    let heads = RefStorage::load(&get_vcs_heads_path(repo))?;
-    if heads.contains(branch_name) {
+    if transaction.heads.contains(branch_name) {
         Err(VcsError::BranchAlreadyExists(branch_name.to_owned()))?;
     }
-    if let Some(current_commit) = state.current_commit {
-        let heads_path = get_vcs_heads_path(repo);
-        let mut heads = RefStorage::load(&heads_path)?;
-        heads.update(branch_name.to_owned(), current_commit);
-        heads.save(&heads_path)?;
-    }
-    state.current_branch = branch_name.to_owned();
-    state.save(&state_path)?;
-    Ok(StateInfo::from(VcsRepositoryState {
-        current_commit: state.current_commit,
-        current_branch: state.current_branch,
-    }))
+    if let Some(current_commit) = transaction.state.current_commit {
+        transaction
+            .heads
+            .update(branch_name.to_owned(), current_commit);
+    }
+    transaction.state.current_branch = branch_name.to_owned();
+    Ok(StateInfo::from(transaction.commit(None)?))
+}
+
+/// Lists the branches in alphabetical order.
+pub fn get_branches(repo: &VcsRepository) -> VcsResult<Vec<BranchInfo>> {
+    let heads = RefStorage::load(&get_vcs_heads_path(repo))?;
+    Ok(heads
+        .iter()
+        .map(|(name, id)| BranchInfo {
+            name: name.to_owned(),
+            head: get_human_id(id),
+        })
+        .collect())
 }
 
 /// Updates STATE to be on the specified commit. Updates working tree by loading
 /// the tree the commit points to. Checks for uncommitted changes.
-pub fn jump_to_commit(repo: &Path, commit_id: &str) -> VcsResult<StateInfo> {
-    let mut index = Index::load(&get_vcs_index_path(repo))?;
-    let changes = get_changed_files(repo, &index)?;
-    if !changes.is_empty() {
-        Err(VcsError::UncomittedChanges {
-            changes: into_pathspec(repo, changes),
-        })?;
-    }
+pub fn jump_to_commit(repo: &VcsRepository, commit_id: &str) -> VcsResult<StateInfo> {
+    let mut transaction = Transaction::begin(repo)?;
+    check_no_changes(repo, &mut transaction.index)?;
 
-    let commit_id = get_inner_id(commit_id)?;
-    let commit = VcsObjects::load(&get_vsc_object_path(repo, &commit_id))?.commit();
-    let tree = VcsObjects::load(&get_vsc_object_path(repo, &commit.tree))?.tree();
-    load_from_tree(repo, &tree, &mut index)?;
-    remove_extra_entries(repo, &index)?;
-    let state = VcsRepositoryState {
+    let (commit_id, commit) = find_commit(repo, commit_id)?;
+    let tree = load_tree(repo, &commit.tree)?;
+    transaction.state = VcsRepositoryState {
         current_branch: commit.branch,
         current_commit: Some(commit_id),
     };
-
-    state.save(&get_vcs_state_path(repo))?;
-    Ok(StateInfo::from(state))
+    Ok(StateInfo::from(transaction.commit(Some(&tree))?))
 }
 
 /// Updates STATE to be on the specified branch and its head commit.
 /// Updates working tree by loading the tree the branch's head commit points to.
 /// Checks for uncommitted changes.
-pub fn jump_to_branch(repo: &Path, branch_name: &str) -> VcsResult<StateInfo> {
-    let heads = RefStorage::load(&get_vcs_heads_path(repo))?;
-    if !heads.contains(branch_name) {
+pub fn jump_to_branch(repo: &VcsRepository, branch_name: &str) -> VcsResult<StateInfo> {
+    let mut transaction = Transaction::begin(repo)?;
+    if !transaction.heads.contains(branch_name) {
         Err(VcsError::NoBranch(branch_name.to_owned()))?;
     }
-    let commit_id = heads.get_id(branch_name);
-    jump_to_commit(repo, &get_human_id(commit_id))?;
-    let state = VcsRepositoryState {
+    check_no_changes(repo, &mut transaction.index)?;
+
+    let commit_id = *transaction.heads.get_id(branch_name);
+    let tree = load_tree(repo, &load_commit(repo, &commit_id)?.tree)?;
+    transaction.state = VcsRepositoryState {
         current_branch: branch_name.to_owned(),
-        current_commit: Some(*commit_id),
+        current_commit: Some(commit_id),
     };
-    state.save(&get_vcs_state_path(repo))?;
-    Ok(StateInfo::from(state))
+    Ok(StateInfo::from(transaction.commit(Some(&tree))?))
+}
+
+/// Fails if the working tree has changes that are not committed.
+fn check_no_changes(repo: &VcsRepository, index: &mut Index) -> VcsResult<()> {
+    let changes = get_changed_files(repo, index)?;
+    if !changes.is_empty() {
+        Err(VcsError::UncomittedChanges {
+            changes: into_pathspec(repo, changes),
+        })?;
+    }
+    Ok(())
 }
 
-/// Forms commit logs from the current commit to the root commit
-pub fn get_commit_logs(repo: &Path) -> VcsResult<Vec<CommitLog>> {
+/// Forms commit logs from the current commit to the root commit, or from all
+/// the branch heads if `filter.all` is set, newest commit first, keeping the
+/// commits the filter accepts. The filter is applied while walking: the walk
+/// stops once enough commits are found or commits get older than `since`, and
+/// changes are only computed for the commits passing the other criteria.
+pub fn get_commit_logs(repo: &VcsRepository, filter: &LogFilter) -> VcsResult<Vec<CommitLog>> {
     let state = VcsRepositoryState::load(&get_vcs_state_path(repo))?;
+    let heads = RefStorage::load(&get_vcs_heads_path(repo))?;
+    let mut start: Vec<_> = state.current_commit.into_iter().collect();
+    if filter.all {
+        start.extend(heads.iter().map(|(_, id)| *id));
+    }
+    // The path of a followed file is replaced by its previous names.
+    let mut filter = filter.clone();
+    let follow = filter.follow && filter.paths.len() == 1;
     let mut logs = Vec::new();
-    let mut current_commit_id = state.current_commit;
-    let (mut current_commit, mut current_tree) = (None, None);
-    while let Some(commit_id) = current_commit_id {
-        let commit = current_commit
-            .unwrap_or(VcsObjects::load(&get_vsc_object_path(repo, &commit_id))?.commit());
-        let tree = current_tree
-            .unwrap_or(VcsObjects::load(&get_vsc_object_path(repo, &commit.tree))?.tree());
-        (current_commit, current_tree) = (None, None);
-
-        let changes = if let Some(parent) = &commit.parent {
-            let parent_commit = VcsObjects::load(&get_vsc_object_path(repo, parent))?.commit();
-            let parent_tree =
-                VcsObjects::load(&get_vsc_object_path(repo, &parent_commit.tree))?.tree();
-            let changes = compare_trees(repo, &parent_tree, &tree)?;
-            current_commit = Some(parent_commit);
-            current_tree = Some(parent_tree);
-            changes
+    for entry in CommitWalk::new(repo, start, !filter.all)? {
+        if filter
+            .max_count
+            .is_some_and(|max_count| logs.len() >= max_count)
+        {
+            break;
+        }
+        let (commit_id, commit) = entry?;
+        let time = DateTime::from(commit.time);
+        // Parents are older than their children.
+        if filter.since.is_some_and(|since| time < since) {
+            break;
+        }
+        let accepted = filter.accepts(&commit.message, &commit.author, &time);
+        if !accepted && !follow {
+            continue;
+        }
+        let changes = into_pathspec(repo, get_commit_changes(repo, &commit)?);
+        // Removed files are not listed in the log, but they count as touched
+        // and may be the previous name of a followed file.
+        let removed = if filter.paths.is_empty() {
+            Vec::new()
         } else {
-            get_tree_files(repo, &tree)?
+            get_commit_removals(repo, &commit)?
         };
+        let touched = filter.touches(&changes)
+            || filter.touches_paths(
+                removed
+                    .iter()
+                    .map(|path| file_manager::get_relative(repo, path)),
+            );
+        if follow && changes.contains(&(FileStatus::Added, filter.paths[0].clone())) {
+            let path = repo.join(&filter.paths[0]);
+            if let Some(source) = find_rename_source(repo, &commit, &removed, &path)? {
+                filter.paths[0] = file_manager::get_relative(repo, &source);
+            }
+        }
+        if !accepted || !touched {
+            continue;
+        }
         logs.push(CommitLog {
             human_id: get_human_id(&commit_id),
-            changes: into_pathspec(repo, changes),
+            changes,
+            parents: commit.parents().map(get_human_id).collect(),
             message: commit.message,
-            time: DateTime::from(commit.time),
+            time,
+            author: commit.author,
+            branches: heads
+                .iter()
+                .filter(|(_, id)| **id == commit_id)
+                .map(|(name, _)| name.to_owned())
+                .collect(),
+            head: state.current_commit == Some(commit_id),
+            stat: None,
         });
-        current_commit_id = commit.parent;
     }
     Ok(logs)
 }
 
+/// Counts the lines each logged commit added and removed in each file it
+/// changed, in relation to its first parent, and stores the counts in the log.
+/// The files counted are the changes of the log. Files that are binary, stored
+/// as chunks or larger than `MAX_DIFF_SIZE` are not counted.
+pub fn add_commit_stats(repo: &VcsRepository, logs: &mut [CommitLog]) -> VcsResult<()> {
+    let read_file = |tree: &Tree, path: &Path| -> VcsResult<Option<Vec<u8>>> {
+        match find_blob(repo, tree, &repo.join(path))? {
+            Some(node) => read_small_blob(repo, &node.id, line_diff::MAX_DIFF_SIZE),
+            None => Ok(Some(Vec::new())),
+        }
+    };
+    for log in logs {
+        let commit = load_commit(repo, &get_inner_id(&log.human_id)?)?;
+        let tree = load_tree(repo, &commit.tree)?;
+        let parent_tree = match &commit.parent {
+            Some(parent) => load_tree(repo, &load_commit(repo, parent)?.tree)?,
+            None => Tree::new(),
+        };
+        let mut stats = Vec::new();
+        for (_, path) in log.changes.iter() {
+            let counts = match (read_file(&parent_tree, path)?, read_file(&tree, path)?) {
+                (Some(old), Some(new))
+                    if !line_diff::is_binary(&old) && !line_diff::is_binary(&new) =>
+                {
+                    Some(line_diff::count_changed_lines(&old, &new))
+                }
+                _ => None,
+            };
+            stats.push(ChangeStat {
+                path: path.to_owned(),
+                added: counts.map(|(added, _)| added),
+                removed: counts.map(|(_, removed)| removed),
+            });
+        }
+        log.stat = Some(stats);
+    }
+    Ok(())
+}
+
+/// Attributes each line of the file to the commit that added it or last
+/// changed it. The file is the one recorded in the commit with the given
+/// public id or in the head of the branch with the given name, or in the
+/// current commit if there is no revision. Its path is relative to the
+/// repository root.
+///
+/// The history is walked back from the revision. The lines a commit kept from
+/// one of its parents are passed on to that parent, the remaining lines are
+/// attributed to the commit. Lines of a merged branch are passed on to the
+/// merged commit.
+pub fn blame_file(
+    repo: &VcsRepository,
+    path: &Path,
+    rev: Option<&str>,
+) -> VcsResult<Vec<BlameLine>> {
+    let start = match rev {
+        Some(rev) => resolve_commit(repo, rev)?.0,
+        None => {
+            let state = VcsRepositoryState::load(&get_vcs_state_path(repo))?;
+            state
+                .current_commit
+                .ok_or_else(|| VcsError::NoCommit("HEAD".to_owned()))?
+        }
+    };
+    let file_path = repo.join(path);
+    let tree = load_tree(repo, &load_commit(repo, &start)?.tree)?;
+    let Some(node) = find_blob(repo, &tree, &file_path)? else {
+        return Err(VcsError::NoFile {
+            path: path.to_owned(),
+            commit: rev.map_or_else(|| get_human_id(&start), str::to_owned),
+        }
+        .into());
+    };
+    let data = read_blob(repo, &node.id)?;
+    let lines = line_diff::split_lines(&data);
+
+    // For each commit to visit, the blob of its version of the file and the
+    // lines of that version still to be attributed, with their numbers in the
+    // blamed file. A commit is queued exactly while it has pending lines, so
+    // one whose timestamp is older than a commit passing it lines later on is
+    // visited again for those lines.
+    let all_lines: Vec<_> = (0..lines.len()).map(|line| (line, line)).collect();
+    let mut pending = BTreeMap::from([(start, (node.id, all_lines))]);
+    let mut queue = BinaryHeap::from([(load_commit(repo, &start)?.time, start)]);
+    let mut origins = vec![None; lines.len()];
+    while let Some((_, commit_id)) = queue.pop() {
+        let Some((blob_id, mut remaining)) = pending.remove(&commit_id) else {
+            continue;
+        };
+        let commit = load_commit(repo, &commit_id)?;
+        let mut version = None;
+        for parent in commit.parents() {
+            if remaining.is_empty() {
+                break;
+            }
+            let parent_commit = load_commit(repo, parent)?;
+            let parent_tree = load_tree(repo, &parent_commit.tree)?;
+            let Some(parent_node) = find_blob(repo, &parent_tree, &file_path)? else {
+                continue;
+            };
+            let kept: Vec<_> = if parent_node.id == blob_id {
+                std::mem::take(&mut remaining)
+            } else {
+                if version.is_none() {
+                    version = Some(read_blob(repo, &blob_id)?);
+                }
+                let parent_version = read_blob(repo, &parent_node.id)?;
+                let kept_lines =
+                    line_diff::map_kept_lines(&parent_version, version.as_deref().unwrap());
+                let (kept, changed) = remaining
+                    .into_iter()
+                    .partition::<Vec<_>, _>(|(line, _)| kept_lines[*line].is_some());
+                remaining = changed;
+                kept.into_iter()
+                    .map(|(line, blamed_line)| (kept_lines[line].unwrap(), blamed_line))
+                    .collect()
+            };
+            if kept.is_empty() {
+                continue;
+            }
+            pending
+                .entry(*parent)
+                .or_insert_with(|| {
+                    queue.push((parent_commit.time, *parent));
+                    (parent_node.id, Vec::new())
+                })
+                .1
+                .extend(kept);
+        }
+        for (_, blamed_line) in remaining {
+            origins[blamed_line] = Some((commit_id, commit.clone()));
+        }
+    }
+
+    let mut blame = Vec::new();
+    for (number, (line, origin)) in lines.iter().zip(origins).enumerate() {
+        let Some((commit_id, commit)) = origin else {
+            return Err(anyhow::anyhow!(
+                "line {} of {} couldn't be attributed to a commit",
+                number + 1,
+                path.display()
+            ));
+        };
+        let content = line.strip_suffix(b"\n").unwrap_or(line);
+        let content = content.strip_suffix(b"\r").unwrap_or(content);
+        blame.push(BlameLine {
+            human_id: get_human_id(&commit_id),
+            author: commit.author,
+            time: DateTime::from(commit.time),
+            line: number + 1,
+            content: String::from_utf8_lossy(content).into_owned(),
+        });
+    }
+    Ok(blame)
+}
+
 /// Merge `branch_name` into MASTER branch. Checks for merge conflics and aborts
 /// iff there are any.
-pub fn merge_branch(repo: &Path, branch_name: &str) -> VcsResult<NewCommitInfo> {
-    let state_path = get_vcs_state_path(repo);
-    let state = VcsRepositoryState::load(&state_path)?;
-
-    let heads_path = get_vcs_heads_path(repo);
-    let heads = RefStorage::load(&heads_path)?;
+pub fn merge_branch(repo: &VcsRepository, branch_name: &str) -> VcsResult<NewCommitInfo> {
+    let mut transaction = Transaction::begin(repo)?;
+    let (state, heads) = (&transaction.state, &transaction.heads);
 
     if !heads.contains(branch_name) {
         Err(VcsError::NoBranch(branch_name.to_owned()))?;
     }
-    let master_head_id = heads.get_id(MASTER_BRANCH);
+    let master_head_id = *heads.get_id(MASTER_BRANCH);
     if state.current_branch != MASTER_BRANCH
-        || heads.contains(MASTER_BRANCH) && state.current_commit.unwrap().ne(master_head_id)
+        || heads.contains(MASTER_BRANCH) && state.current_commit != Some(master_head_id)
     {
         Err(VcsError::MergeFromNotMasterHead)?;
     }
-    let index_path = get_vcs_index_path(repo);
-    let index = Index::load(&index_path)?;
-    let changes = get_changed_files(repo, &index)?;
-    if !changes.is_empty() {
-        Err(VcsError::UncomittedChanges {
-            changes: into_pathspec(repo, changes),
-        })?;
-    }
-    let branch_head_id = heads.get_id(branch_name);
-    let branch_head = VcsObjects::load(&get_vsc_object_path(repo, branch_head_id))?.commit();
-    let branch_tree = VcsObjects::load(&get_vsc_object_path(repo, &branch_head.tree))?.tree();
+    let branch_head_id = *heads.get_id(branch_name);
+    let branch_head = load_commit(repo, &branch_head_id)?;
+    check_no_changes(repo, &mut transaction.index)?;
+    let branch_tree = load_tree(repo, &branch_head.tree)?;
 
     let root_commit = get_branch_root(repo, branch_name, branch_head)?;
-    let root_tree = VcsObjects::load(&get_vsc_object_path(repo, &root_commit.tree))?.tree();
+    let root_tree = load_tree(repo, &root_commit.tree)?;
 
-    let master_head = VcsObjects::load(&get_vsc_object_path(repo, master_head_id))?.commit();
-    let master_tree = VcsObjects::load(&get_vsc_object_path(repo, &master_head.tree))?.tree();
+    let master_head = load_commit(repo, &master_head_id)?;
+    let master_tree = load_tree(repo, &master_head.tree)?;
 
     let branch_changes = compare_trees(repo, &root_tree, &branch_tree)?;
     let master_changes = compare_trees(repo, &root_tree, &master_tree)?;
     let intersection = branch_changes.intersect(master_changes);
     if !intersection.is_empty() {
         Err(VcsError::MergeConflict {
-            both_changed: intersection,
+            both_changed: into_pathspec(repo, intersection),
         })?;
     }
-    let merged_tree = VcsObjects::Tree(merge_trees(branch_tree, master_tree));
-    let content = merged_tree.get_content()?;
-    let merged_tree_id = get_vcs_object_id(&content);
-    file_manager::write_file(&get_vsc_object_path(repo, &merged_tree_id), &content)?;
+    let merged_tree = merge_trees(branch_tree, master_tree);
+    let merged_tree_id = write_object(repo, &VcsObjects::Tree(merged_tree.clone()))?;
 
     let merge_commit = Commit {
         tree: merged_tree_id,
-        parent: Some(*master_head_id),
+        parent: Some(master_head_id),
         branch: MASTER_BRANCH.to_owned(),
         message: format!("Merged branch {}", branch_name),
         time: SystemTime::now(),
+        author: get_author(repo),
+        merged: vec![branch_head_id],
     };
     let commit_id = record_commit(repo, &merge_commit)?;
 
-    let mut heads = heads;
-    heads.update(MASTER_BRANCH.to_owned(), commit_id);
-    heads.save(&heads_path)?;
-
-    let mut state = state;
-    state.current_commit = Some(commit_id);
-    state.save(&state_path)?;
-
-    let mut index = index;
-    load_from_tree(repo, &merged_tree.tree(), &mut index)?;
-    index.save(&index_path)?;
+    transaction
+        .heads
+        .update(MASTER_BRANCH.to_owned(), commit_id);
+    transaction.state.current_commit = Some(commit_id);
+    transaction.commit(Some(&merged_tree))?;
 
     Ok(NewCommitInfo {
         human_id: get_human_id(&commit_id),
@@ -300,22 +518,197 @@ pub fn merge_branch(repo: &Path, branch_name: &str) -> VcsResult<NewCommitInfo>
     })
 }
 
+/// Reads the object with the given public id from the objects database.
+pub fn get_object(repo: &VcsRepository, object_id: &str) -> VcsResult<ObjectInfo> {
+    let (_, object) = find_object(repo, object_id)?;
+    Ok(match object {
+        VcsObjects::Commit(commit) => ObjectInfo::Commit {
+            tree: get_human_id(&commit.tree),
+            parent: commit.parent.map(|id| get_human_id(&id)),
+            branch: commit.branch,
+            message: commit.message,
+            time: DateTime::from(commit.time),
+            author: commit.author,
+            merged: commit.merged.iter().map(get_human_id).collect(),
+        },
+        VcsObjects::Blob(blob) => ObjectInfo::Blob(blob.data),
+        VcsObjects::Tree(tree) => ObjectInfo::Tree(get_tree_entries(repo, &tree, false)?),
+        VcsObjects::Chunk(chunk) => ObjectInfo::Chunk(chunk.data),
+        VcsObjects::ChunkList(chunk_list) => {
+            ObjectInfo::ChunkList(chunk_list.chunks.iter().map(get_human_id).collect())
+        }
+    })
+}
+
+/// Computes the id of the Blob object the file would be recorded as. The blob
+/// is also written to the objects database iff `write` is set.
+pub fn hash_file(repo: &VcsRepository, file_path: &Path, write: bool) -> VcsResult<String> {
+    assert!(file_path.is_absolute());
+    if !file_path.starts_with(repo) || file_path.starts_with(get_vcs_root(repo)) {
+        Err(VcsError::OutsideRepository(file_path.to_owned()))?;
+    }
+    let mode = file_manager::get_file_mode(file_path)?;
+    let id = if write {
+        let _lock = RepositoryLock::acquire(repo)?;
+        let id = write_blob(repo, file_path, mode)?;
+        repo.store().flush()?;
+        id
+    } else {
+        hash_blob(repo, file_path, mode, should_chunk(repo, file_path, mode)?)?
+    };
+    Ok(get_human_id(&id))
+}
+
+/// Lists the tree a tree-ish (a branch name, a commit id or a tree id) points
+/// to. Subtrees are expanded iff `recursive` is set.
+pub fn list_tree(
+    repo: &VcsRepository,
+    tree_ish: &str,
+    recursive: bool,
+) -> VcsResult<Vec<TreeEntryInfo>> {
+    let tree = resolve_tree(repo, tree_ish)?;
+    get_tree_entries(repo, &tree, recursive)
+}
+
+/// Verifies the integrity of the repository. Every object's contents must
+/// match the hash in its path, every reference between objects must point to
+/// an existing object of the expected type and branch heads and STATE must
+/// point to existing commits. Fails iff any of those checks fails. Otherwise,
+/// returns dangling and unreachable objects.
+pub fn check_repository(repo: &VcsRepository) -> VcsResult<Vec<IntegrityIssue>> {
+    let mut issues = Vec::new();
+    let mut objects = BTreeMap::new();
+    for stored in repo.store().iter()? {
+        let path = get_stored_path(repo, stored.location);
+        let Some(id) = stored.id else {
+            issues.push(IntegrityIssue::Corrupt { path });
+            continue;
+        };
+        // Only the references are parsed. The data of blobs and chunks is
+        // checked through the hash alone, without loading it in memory.
+        match read_links(repo, &id) {
+            Ok(links) => {
+                if hash_stored_object(repo, &id)? != id {
+                    issues.push(IntegrityIssue::HashMismatch { path });
+                }
+                objects.insert(id, links);
+            }
+            Err(_) => issues.push(IntegrityIssue::Corrupt { path }),
+        }
+    }
+
+    let mut referenced = BTreeSet::new();
+    for (id, object) in objects.iter() {
+        let referrer = format!("{} {}", object.kind, get_human_id(id));
+        for (reference, expected) in object.references.iter() {
+            issues.extend(check_reference(&objects, reference, expected, &referrer));
+            referenced.insert(*reference);
+        }
+    }
+    let roots = get_roots(repo)?;
+    for (referrer, id) in roots.iter() {
+        issues.extend(check_reference(&objects, id, "commit", referrer));
+    }
+
+    let reachable = mark_reachable(roots.into_iter().map(|(_, id)| id), &objects);
+    for (id, object) in objects.iter() {
+        if reachable.contains(id) {
+            continue;
+        }
+        let (kind, human_id) = (object.kind, get_human_id(id));
+        issues.push(if referenced.contains(id) {
+            IntegrityIssue::Unreachable { kind, human_id }
+        } else {
+            IntegrityIssue::Dangling { kind, human_id }
+        });
+    }
+    if issues.iter().any(IntegrityIssue::is_error) {
+        return Err(VcsError::CorruptRepository { issues }.into());
+    }
+    Ok(issues)
+}
+
+/// Removes the objects that can't be reached from branch heads and STATE from
+/// the objects database, together with the temporary files of object writes
+/// that didn't complete. Objects and files modified less than `grace_period`
+/// ago are kept, so that objects written by a commit in progress are not lost.
+pub fn collect_garbage(repo: &VcsRepository, grace_period: Duration) -> VcsResult<GarbageInfo> {
+    let _lock = RepositoryLock::acquire(repo)?;
+    let mut objects = BTreeMap::new();
+    let mut stored_objects = BTreeMap::new();
+    let mut corrupt_objects = Vec::new();
+    for stored in repo.store().iter()? {
+        let Some(id) = stored.id else {
+            continue;
+        };
+        // Corrupt objects are left alone, the references they hold are lost
+        // anyway.
+        match read_links(repo, &id) {
+            Ok(links) => {
+                objects.insert(id, links);
+                stored_objects.insert(id, stored);
+            }
+            Err(_) => corrupt_objects.push(get_stored_path(repo, stored.location)),
+        }
+    }
+    let roots = get_roots(repo)?;
+    let reachable = mark_reachable(roots.into_iter().map(|(_, id)| id), &objects);
+
+    let now = SystemTime::now();
+    let mut info = GarbageInfo {
+        removed_objects: 0,
+        freed_bytes: 0,
+        corrupt_objects,
+    };
+    let mut garbage = Vec::new();
+    for (id, stored) in stored_objects.iter() {
+        if reachable.contains(id) {
+            continue;
+        }
+        let age = now.duration_since(stored.modified).unwrap_or_default();
+        if age < grace_period {
+            continue;
+        }
+        garbage.push(*id);
+        info.removed_objects += 1;
+        info.freed_bytes += stored.size;
+    }
+    repo.store().remove(&garbage)?;
+    info.freed_bytes += remove_temp_files(repo, grace_period)?;
+    Ok(info)
+}
+
+/// Returns the value of the repository setting.
+pub fn get_setting(repo: &VcsRepository, key: &str) -> VcsResult<serde_json::Value> {
+    Ok(repo.config().get(key)?)
+}
+
+/// Updates the repository setting.
+pub fn set_setting(repo: &mut VcsRepository, key: &str, value: &str) -> VcsResult<()> {
+    let _lock = RepositoryLock::acquire(repo)?;
+    let mut config = load_config(repo)?;
+    config.set(key, value)?;
+    config.save(&get_vcs_config_path(repo))?;
+    repo.set_config(config);
+    Ok(())
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
-    use assert_fs::prelude::{FileTouch, PathAssert, PathChild, PathCreateDir};
+    use assert_fs::prelude::{FileTouch, FileWriteStr, PathAssert, PathChild, PathCreateDir};
 
     #[test]
     fn test_init() {
         let tmp_dir = assert_fs::TempDir::new().unwrap();
-        assert!(init_vcs_directory(&tmp_dir).is_ok());
+        assert!(init_vcs_directory(&tmp_dir, HashAlgorithm::Sha1, ObjectStoreKind::Loose).is_ok());
     }
 
     #[test]
     fn test_double_init() {
         let tmp_dir = assert_fs::TempDir::new().unwrap();
-        assert!(init_vcs_directory(&tmp_dir).is_ok());
-        assert!(init_vcs_directory(&tmp_dir).is_err());
+        assert!(init_vcs_directory(&tmp_dir, HashAlgorithm::Sha1, ObjectStoreKind::Loose).is_ok());
+        assert!(init_vcs_directory(&tmp_dir, HashAlgorithm::Sha1, ObjectStoreKind::Loose).is_err());
     }
 
     #[test]
@@ -345,7 +738,8 @@ mod tests {
         tmp_dir.child("file1").touch().unwrap();
         tmp_dir.child("subdir/file2").touch().unwrap();
 
-        let changes = get_changes(tmp_dir.path()).unwrap();
+        let repo = VcsRepository::open(tmp_dir.to_path_buf()).unwrap();
+        let changes = get_changes(&repo).unwrap();
         assert_eq!(
             changes,
             [
@@ -354,4 +748,305 @@ mod tests {
             ]
         )
     }
+
+    #[test]
+    fn test_recover_checkout() {
+        let tmp_dir = assert_fs::TempDir::new().unwrap();
+        init_vcs_directory(&tmp_dir, HashAlgorithm::Sha1, ObjectStoreKind::Loose).unwrap();
+        let repo = &VcsRepository::open(tmp_dir.to_path_buf()).unwrap();
+        tmp_dir.child("file").write_str("first").unwrap();
+        let first = make_commit(repo, "first").unwrap();
+        tmp_dir.child("file").write_str("second").unwrap();
+        tmp_dir.child("dir/added").touch().unwrap();
+        make_commit(repo, "second").unwrap();
+        // Created after the interrupted checkout, so it must be kept.
+        tmp_dir.child("untracked").touch().unwrap();
+
+        let journal = Journal {
+            state: VcsRepositoryState {
+                current_commit: Some(get_inner_id(&first.human_id).unwrap()),
+                current_branch: MASTER_BRANCH.to_owned(),
+            },
+            heads: None,
+            checkout: true,
+            rolled_back: None,
+        };
+        begin_update(repo, &journal).unwrap();
+        assert_eq!(&*open_repository(repo).unwrap(), tmp_dir.path());
+
+        tmp_dir.child("file").assert("first");
+        tmp_dir.child("dir").assert(predicates::path::missing());
+        tmp_dir
+            .child("untracked")
+            .assert(predicates::path::exists());
+        assert_eq!(get_state(repo).unwrap().commit, Some(first.human_id));
+        assert_eq!(
+            get_changes(repo).unwrap(),
+            [(FileStatus::Added, PathBuf::from("untracked"))]
+        );
+        assert!(!get_vcs_journal_path(repo).exists());
+    }
+
+    #[test]
+    fn test_recover_branching() {
+        let tmp_dir = assert_fs::TempDir::new().unwrap();
+        init_vcs_directory(&tmp_dir, HashAlgorithm::Sha1, ObjectStoreKind::Loose).unwrap();
+        let repo = &VcsRepository::open(tmp_dir.to_path_buf()).unwrap();
+        tmp_dir.child("file").write_str("first").unwrap();
+        let first = make_commit(repo, "first").unwrap();
+        tmp_dir.child("file").write_str("second").unwrap();
+
+        let first_id = get_inner_id(&first.human_id).unwrap();
+        let mut heads = RefStorage::load(&get_vcs_heads_path(repo)).unwrap();
+        heads.update("feature".to_owned(), first_id);
+        let journal = Journal {
+            state: VcsRepositoryState {
+                current_commit: Some(first_id),
+                current_branch: "feature".to_owned(),
+            },
+            heads: Some(heads),
+            checkout: false,
+            rolled_back: None,
+        };
+        begin_update(repo, &journal).unwrap();
+        open_repository(repo).unwrap();
+
+        tmp_dir.child("file").assert("second");
+        assert_eq!(get_state(repo).unwrap().branch, "feature");
+        assert!(RefStorage::load(&get_vcs_heads_path(repo))
+            .unwrap()
+            .contains("feature"));
+        assert_eq!(
+            get_changes(repo).unwrap(),
+            [(FileStatus::Modified, PathBuf::from("file"))]
+        );
+    }
+
+    #[test]
+    fn test_stat_cache() {
+        let tmp_dir = assert_fs::TempDir::new().unwrap();
+        tmp_dir.child("file").write_str("first").unwrap();
+        // Let the file's timestamps get older than the current second, so that
+        // its stat data is not racy.
+        std::thread::sleep(Duration::from_millis(1100));
+        init_vcs_directory(&tmp_dir, HashAlgorithm::Sha1, ObjectStoreKind::Loose).unwrap();
+        let repo = &VcsRepository::open(tmp_dir.to_path_buf()).unwrap();
+        make_commit(repo, "first").unwrap();
+        tmp_dir.child("racy").write_str("racy").unwrap();
+        make_commit(repo, "second").unwrap();
+
+        let index_path = get_vcs_index_path(repo);
+        let mut index = Index::load(&index_path).unwrap();
+        let file = tmp_dir.join("file");
+        assert!(index.get(&file).stat.is_some());
+        assert!(index.get(&tmp_dir.join("racy")).stat.is_none());
+
+        // A file with unchanged stat data is not hashed.
+        let entry = *index.get(&file);
+        let other_id = get_inner_id(&hash_file(repo, &tmp_dir.join("racy"), false).unwrap());
+        index.update(
+            file.clone(),
+            IndexEntry {
+                id: other_id.unwrap(),
+                ..entry
+            },
+        );
+        index.save(&index_path).unwrap();
+        assert!(get_changes(repo).unwrap().is_empty());
+
+        tmp_dir.child("file").write_str("fir5t").unwrap();
+        assert_eq!(
+            get_changes(repo).unwrap(),
+            [(FileStatus::Modified, PathBuf::from("file"))]
+        );
+    }
+
+    #[test]
+    fn test_status_while_locked() {
+        let tmp_dir = assert_fs::TempDir::new().unwrap();
+        init_vcs_directory(&tmp_dir, HashAlgorithm::Sha1, ObjectStoreKind::Loose).unwrap();
+        let repo = &VcsRepository::open(tmp_dir.to_path_buf()).unwrap();
+        tmp_dir.child("file").write_str("first").unwrap();
+        make_commit(repo, "first").unwrap();
+        // Let the file's stat data get old enough to be recorded.
+        std::thread::sleep(Duration::from_millis(1100));
+        let index_path = get_vcs_index_path(repo);
+        let file = tmp_dir.join("file");
+        let mut index = Index::load(&index_path).unwrap();
+        let entry = *index.get(&file);
+        index.update(
+            file.clone(),
+            IndexEntry {
+                stat: None,
+                ..entry
+            },
+        );
+        index.save(&index_path).unwrap();
+
+        // The refreshed stat data is dropped while another process holds the
+        // lock.
+        let lock = RepositoryLock::acquire(repo).unwrap();
+        assert!(get_changes(repo).unwrap().is_empty());
+        assert!(Index::load(&index_path).unwrap().get(&file).stat.is_none());
+        drop(lock);
+
+        assert!(get_changes(repo).unwrap().is_empty());
+        assert!(Index::load(&index_path).unwrap().get(&file).stat.is_some());
+    }
+
+    #[test]
+    fn test_transaction() {
+        let tmp_dir = assert_fs::TempDir::new().unwrap();
+        init_vcs_directory(&tmp_dir, HashAlgorithm::Sha1, ObjectStoreKind::Loose).unwrap();
+        let repo = &VcsRepository::open(tmp_dir.to_path_buf()).unwrap();
+        tmp_dir.child("file").touch().unwrap();
+        let first = make_commit(repo, "first").unwrap();
+
+        let mut transaction = Transaction::begin(repo).unwrap();
+        assert!(Transaction::begin(repo).is_err());
+        transaction.state.current_branch = "feature".to_owned();
+        transaction.index.clear();
+        drop(transaction);
+        assert_eq!(get_state(repo).unwrap().branch, MASTER_BRANCH);
+        assert!(get_changes(repo).unwrap().is_empty());
+
+        let mut transaction = Transaction::begin(repo).unwrap();
+        transaction.state.current_branch = "feature".to_owned();
+        let state = transaction.commit(None).unwrap();
+        assert_eq!(state.current_branch, "feature");
+        assert_eq!(get_state(repo).unwrap().commit, Some(first.human_id));
+        assert_eq!(get_state(repo).unwrap().branch, "feature");
+        assert!(!get_vcs_journal_path(repo).exists());
+    }
+
+    #[test]
+    fn test_object_cache() {
+        let tmp_dir = assert_fs::TempDir::new().unwrap();
+        init_vcs_directory(&tmp_dir, HashAlgorithm::Sha1, ObjectStoreKind::Loose).unwrap();
+        let repo = &VcsRepository::open(tmp_dir.to_path_buf()).unwrap();
+        tmp_dir.child("file").touch().unwrap();
+        let commit = make_commit(repo, "first").unwrap();
+        let commit_id = get_inner_id(&commit.human_id).unwrap();
+        let tree_id = load_commit(repo, &commit_id).unwrap().tree;
+        let blob_id = hash_file(repo, &tmp_dir.join("file"), false).unwrap();
+        let blob_id = get_inner_id(&blob_id).unwrap();
+        load_tree(repo, &tree_id).unwrap();
+        load_object(repo, &blob_id).unwrap();
+
+        // Commits and trees are not read again, blobs are.
+        fs::remove_dir_all(get_vcs_objects_path(repo)).unwrap();
+        assert_eq!(load_commit(repo, &commit_id).unwrap().message, "first");
+        assert!(load_tree(repo, &tree_id).is_ok());
+        assert!(load_object(repo, &blob_id).is_err());
+    }
+
+    #[test]
+    fn test_memory_store() {
+        let tmp_dir = assert_fs::TempDir::new().unwrap();
+        init_vcs_directory(&tmp_dir, HashAlgorithm::Sha1, ObjectStoreKind::Loose).unwrap();
+        let root = tmp_dir.to_path_buf();
+        let config = load_config(&root).unwrap();
+        let repo =
+            &VcsRepository::with_store(root, config, Box::new(object_store::MemoryStore::new()));
+        tmp_dir.child("file").write_str("first").unwrap();
+        let first = make_commit(repo, "first").unwrap();
+        tmp_dir.child("file").write_str("second").unwrap();
+        make_commit(repo, "second").unwrap();
+        assert!(!get_vcs_objects_path(repo).exists());
+
+        jump_to_commit(repo, &first.human_id).unwrap();
+        tmp_dir.child("file").assert("first");
+        assert!(check_repository(repo).unwrap().is_empty());
+
+        jump_to_branch(repo, MASTER_BRANCH).unwrap();
+        let info = collect_garbage(repo, Duration::ZERO).unwrap();
+        assert_eq!(info.removed_objects, 0);
+        assert_eq!(repo.store().iter().unwrap().count(), 6);
+    }
+
+    #[test]
+    fn test_database_store_flush() {
+        use object_store::ObjectStore;
+        let tmp_dir = assert_fs::TempDir::new().unwrap();
+        init_vcs_directory(&tmp_dir, HashAlgorithm::Sha1, ObjectStoreKind::Database).unwrap();
+        let repo = &VcsRepository::open(tmp_dir.to_path_buf()).unwrap();
+        let other = object_store::DatabaseStore::open(&tmp_dir).unwrap();
+        tmp_dir.child("file").write_str("first").unwrap();
+        let commit = make_commit(repo, "first").unwrap();
+        assert!(other
+            .contains(&get_inner_id(&commit.human_id).unwrap())
+            .unwrap());
+
+        let content = b"{\"Chunk\":{\"data\":[]}}".to_vec();
+        let id = get_object_id(repo, &content).unwrap();
+        repo.store().put(&id, &content).unwrap();
+        assert_eq!(repo.store().get(&id).unwrap(), Some(content));
+        assert!(!other.contains(&id).unwrap());
+        repo.store().flush().unwrap();
+        assert!(other.contains(&id).unwrap());
+    }
+
+    #[test]
+    fn test_config_reloaded_on_transaction() {
+        let tmp_dir = assert_fs::TempDir::new().unwrap();
+        init_vcs_directory(&tmp_dir, HashAlgorithm::Sha1, ObjectStoreKind::Loose).unwrap();
+        let repo = &VcsRepository::open(tmp_dir.to_path_buf()).unwrap();
+        let mut other = VcsRepository::open(tmp_dir.to_path_buf()).unwrap();
+        set_setting(&mut other, "author", "alice").unwrap();
+        assert_ne!(repo.config().author, "alice");
+        tmp_dir.child("file").touch().unwrap();
+        let commit = make_commit(repo, "first").unwrap();
+        assert_eq!(repo.config().author, "alice");
+        let id = get_inner_id(&commit.human_id).unwrap();
+        assert_eq!(load_commit(repo, &id).unwrap().author, "alice");
+    }
+
+    #[test]
+    fn test_blame_skewed_times() {
+        let tmp_dir = assert_fs::TempDir::new().unwrap();
+        init_vcs_directory(&tmp_dir, HashAlgorithm::Sha1, ObjectStoreKind::Loose).unwrap();
+        let repo = &VcsRepository::open(tmp_dir.to_path_buf()).unwrap();
+        let mut commits = Vec::new();
+        for content in ["1\n", "1\n2\n", "x\n"] {
+            tmp_dir.child("file").write_str(content).unwrap();
+            let id = get_inner_id(&make_commit(repo, content).unwrap().human_id).unwrap();
+            commits.push((id, load_commit(repo, &id).unwrap()));
+        }
+        let (first_id, first) = &commits[0];
+
+        // The side branch commit is timestamped before its parent, so a walk
+        // by time visits the parent before the side branch passes it a line.
+        let side = record_commit(
+            repo,
+            &Commit {
+                parent: Some(*first_id),
+                time: first.time - Duration::from_secs(1000),
+                ..commits[1].1.clone()
+            },
+        )
+        .unwrap();
+        let master = record_commit(
+            repo,
+            &Commit {
+                parent: Some(*first_id),
+                time: first.time + Duration::from_secs(10),
+                ..commits[2].1.clone()
+            },
+        )
+        .unwrap();
+        let merge = record_commit(
+            repo,
+            &Commit {
+                parent: Some(master),
+                merged: vec![side],
+                time: first.time + Duration::from_secs(20),
+                ..commits[1].1.clone()
+            },
+        )
+        .unwrap();
+
+        let blame = blame_file(repo, Path::new("file"), Some(&get_human_id(&merge))).unwrap();
+        let origins: Vec<_> = blame.iter().map(|line| line.human_id.clone()).collect();
+        assert_eq!(origins, [get_human_id(first_id), get_human_id(&side)]);
+    }
 }
@@ -0,0 +1,305 @@
+use super::objects::{HashAlgorithm, ObjectHasher, VcsObjectId};
+use super::raw_path;
+use crate::errors::{VcsError, VcsResult};
+
+use serde::de::{self, DeserializeSeed, Deserializer, IgnoredAny, MapAccess, SeqAccess, Visitor};
+use serde::ser::{self, SerializeSeq, SerializeStruct, Serializer};
+use serde::Serialize;
+use std::cell::RefCell;
+use std::fmt;
+use std::io::{self, Read, Write};
+use std::path::Path;
+
+const BUFFER_SIZE: usize = 64 * 1024;
+const VARIANTS: &[&str] = &["Commit", "Blob", "Tree", "Chunk", "ChunkList"];
+
+/// Writes a Blob object with the data read from `data` in the same format as
+/// a serialized `VcsObjects::Blob`, without loading the data in memory.
+pub fn write_blob_object(writer: impl Write, file_name: &Path, data: impl Read) -> VcsResult<()> {
+    let blob = BlobStream {
+        file_name,
+        data: RefCell::new(data),
+    };
+    let mut serializer = serde_json::Serializer::new(writer);
+    serializer.serialize_newtype_variant("VcsObjects", 1, "Blob", &blob)?;
+    Ok(())
+}
+
+/// Reads a serialized Blob object and writes its data to `writer`, without
+/// loading the data in memory. Returns the type of the object if it is not a
+/// Blob.
+pub fn read_blob_object(reader: impl Read, writer: impl Write) -> VcsResult<Option<&'static str>> {
+    let mut deserializer = serde_json::Deserializer::from_reader(reader);
+    let found = ObjectSeed(RefCell::new(writer)).deserialize(&mut deserializer)?;
+    Ok(found)
+}
+
+struct BlobStream<'a, R> {
+    file_name: &'a Path,
+    data: RefCell<R>,
+}
+
+impl<R: Read> Serialize for BlobStream<'_, R> {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        let mut blob = serializer.serialize_struct("Blob", 2)?;
+        blob.serialize_field("file_name", &RawPath(self.file_name))?;
+        blob.serialize_field("data", &DataStream(&self.data))?;
+        blob.end()
+    }
+}
+
+struct RawPath<'a>(&'a Path);
+
+impl Serialize for RawPath<'_> {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        raw_path::serialize(self.0, serializer)
+    }
+}
+
+struct DataStream<'a, R>(&'a RefCell<R>);
+
+impl<R: Read> Serialize for DataStream<'_, R> {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        let mut reader = self.0.borrow_mut();
+        let mut seq = serializer.serialize_seq(None)?;
+        let mut buffer = vec![0; BUFFER_SIZE];
+        loop {
+            let read = match reader.read(&mut buffer) {
+                Ok(0) => break,
+                Ok(read) => read,
+                Err(err) if err.kind() == io::ErrorKind::Interrupted => continue,
+                Err(err) => return Err(ser::Error::custom(err)),
+            };
+            for byte in &buffer[..read] {
+                seq.serialize_element(byte)?;
+            }
+        }
+        seq.end()
+    }
+}
+
+/// Accepts a serialized `VcsObjects` and streams the data of a Blob to the
+/// writer.
+struct ObjectSeed<W>(RefCell<W>);
+
+impl<'de, W: Write> DeserializeSeed<'de> for ObjectSeed<W> {
+    type Value = Option<&'static str>;
+
+    fn deserialize<D: Deserializer<'de>>(self, deserializer: D) -> Result<Self::Value, D::Error> {
+        deserializer.deserialize_map(self)
+    }
+}
+
+impl<'de, W: Write> Visitor<'de> for ObjectSeed<W> {
+    type Value = Option<&'static str>;
+
+    fn expecting(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+        formatter.write_str("a vcs object")
+    }
+
+    fn visit_map<A: MapAccess<'de>>(self, mut map: A) -> Result<Self::Value, A::Error> {
+        let kind: String = map
+            .next_key()?
+            .ok_or_else(|| de::Error::custom("empty object"))?;
+        match kind.as_str() {
+            "Blob" => {
+                map.next_value_seed(BlobSeed(&self.0))?;
+                Ok(None)
+            }
+            _ => {
+                let found = match kind.as_str() {
+                    "Commit" => "commit",
+                    "Tree" => "tree",
+                    "Chunk" => "chunk",
+                    "ChunkList" => "chunklist",
+                    _ => return Err(de::Error::unknown_variant(&kind, VARIANTS)),
+                };
+                map.next_value::<IgnoredAny>()?;
+                Ok(Some(found))
+            }
+        }
+    }
+}
+
+struct BlobSeed<'a, W>(&'a RefCell<W>);
+
+impl<'de, W: Write> DeserializeSeed<'de> for BlobSeed<'_, W> {
+    type Value = ();
+
+    fn deserialize<D: Deserializer<'de>>(self, deserializer: D) -> Result<(), D::Error> {
+        deserializer.deserialize_map(self)
+    }
+}
+
+impl<'de, W: Write> Visitor<'de> for BlobSeed<'_, W> {
+    type Value = ();
+
+    fn expecting(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+        formatter.write_str("a blob")
+    }
+
+    fn visit_map<A: MapAccess<'de>>(self, mut map: A) -> Result<(), A::Error> {
+        while let Some(key) = map.next_key::<String>()? {
+            if key == "data" {
+                map.next_value_seed(DataSeed(self.0))?;
+            } else {
+                map.next_value::<IgnoredAny>()?;
+            }
+        }
+        Ok(())
+    }
+}
+
+struct DataSeed<'a, W>(&'a RefCell<W>);
+
+impl<'de, W: Write> DeserializeSeed<'de> for DataSeed<'_, W> {
+    type Value = ();
+
+    fn deserialize<D: Deserializer<'de>>(self, deserializer: D) -> Result<(), D::Error> {
+        deserializer.deserialize_seq(self)
+    }
+}
+
+impl<'de, W: Write> Visitor<'de> for DataSeed<'_, W> {
+    type Value = ();
+
+    fn expecting(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+        formatter.write_str("an array of bytes")
+    }
+
+    fn visit_seq<A: SeqAccess<'de>>(self, mut seq: A) -> Result<(), A::Error> {
+        let mut writer = self.0.borrow_mut();
+        let mut buffer = Vec::with_capacity(BUFFER_SIZE);
+        while let Some(byte) = seq.next_element::<u8>()? {
+            buffer.push(byte);
+            if buffer.len() == BUFFER_SIZE {
+                writer.write_all(&buffer).map_err(de::Error::custom)?;
+                buffer.clear();
+            }
+        }
+        writer.write_all(&buffer).map_err(de::Error::custom)
+    }
+}
+
+/// Writer computing the id of the data written through it.
+pub struct HashingWriter<W> {
+    inner: W,
+    hasher: ObjectHasher,
+}
+
+impl<W: Write> HashingWriter<W> {
+    pub fn new(inner: W, algorithm: HashAlgorithm) -> Self {
+        Self {
+            inner,
+            hasher: ObjectHasher::new(algorithm),
+        }
+    }
+
+    /// Returns the underlying writer and the id of the data written.
+    pub fn finish(self) -> (W, VcsObjectId) {
+        (self.inner, self.hasher.finish())
+    }
+}
+
+impl<W: Write> Write for HashingWriter<W> {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        let written = self.inner.write(buf)?;
+        self.hasher.update(&buf[..written]);
+        Ok(written)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.inner.flush()
+    }
+}
+
+/// Reader computing the id of the data read through it.
+pub struct HashingReader<R> {
+    inner: R,
+    hasher: ObjectHasher,
+}
+
+impl<R: Read> HashingReader<R> {
+    pub fn new(inner: R, algorithm: HashAlgorithm) -> Self {
+        Self {
+            inner,
+            hasher: ObjectHasher::new(algorithm),
+        }
+    }
+
+    /// Reads the rest of the data and returns its id.
+    pub fn finish(mut self) -> io::Result<VcsObjectId> {
+        io::copy(&mut self, &mut io::sink())?;
+        Ok(self.hasher.finish())
+    }
+}
+
+impl<R: Read> Read for HashingReader<R> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        let read = self.inner.read(buf)?;
+        self.hasher.update(&buf[..read]);
+        Ok(read)
+    }
+}
+
+/// Checks that the data read matches the expected object id.
+pub fn verify<R: Read>(reader: HashingReader<R>, id: &VcsObjectId, path: &Path) -> VcsResult<()> {
+    if reader.finish()? != *id {
+        Err(VcsError::CorruptObject(path.to_owned()))?;
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::vcs_manager::objects::{Blob, VcsObjects};
+    use crate::vcs_manager::traits::VcsSerialize;
+    use std::path::PathBuf;
+
+    #[test]
+    fn test_blob_stream() {
+        let data: Vec<u8> = (0..200_000).map(|i| (i % 251) as u8).collect();
+        let blob = VcsObjects::Blob(Blob {
+            file_name: PathBuf::from("dir/file"),
+            data: data.clone(),
+        });
+        let content = blob.get_content().unwrap();
+
+        let mut streamed = Vec::new();
+        write_blob_object(&mut streamed, Path::new("dir/file"), &data[..]).unwrap();
+        assert_eq!(streamed, content);
+
+        let mut read = Vec::new();
+        assert_eq!(read_blob_object(&content[..], &mut read).unwrap(), None);
+        assert_eq!(read, data);
+    }
+
+    #[test]
+    fn test_not_a_blob() {
+        let tree = VcsObjects::Tree(crate::vcs_manager::objects::Tree::new());
+        let content = tree.get_content().unwrap();
+        let mut read = Vec::new();
+        assert_eq!(
+            read_blob_object(&content[..], &mut read).unwrap(),
+            Some("tree")
+        );
+        assert!(read.is_empty());
+    }
+
+    #[test]
+    fn test_hashing() {
+        let content = b"hello world".to_vec();
+        let expected =
+            crate::vcs_manager::objects::get_vcs_object_id(&content, HashAlgorithm::Sha1);
+
+        let mut writer = HashingWriter::new(Vec::new(), HashAlgorithm::Sha1);
+        writer.write_all(&content).unwrap();
+        assert_eq!(writer.finish(), (content.clone(), expected));
+
+        let mut reader = HashingReader::new(&content[..], HashAlgorithm::Sha1);
+        let mut prefix = [0; 5];
+        reader.read_exact(&mut prefix).unwrap();
+        assert_eq!(reader.finish().unwrap(), expected);
+    }
+}
@@ -1,21 +1,278 @@
+use super::objects::{FileMode, FileStat};
 use super::objects_manager::VCS_ROOT;
+use super::raw_path;
 use crate::errors::VcsResult;
 
 use anyhow::Context;
-use std::fs;
+use std::fs::{self, File};
+use std::io::{BufReader, BufWriter, Cursor, Read, Write};
 use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicUsize, Ordering};
 use walkdir::{DirEntry, WalkDir};
 
 pub fn read_file(path: &Path) -> VcsResult<Vec<u8>> {
     fs::read(path).with_context(|| format!("Failed to read {}", path.display()))
 }
 
-pub fn write_file(path: &Path, data: &Vec<u8>) -> VcsResult<()> {
+/// Writes data to the file atomically: the data is written and flushed to a
+/// temporary file in the same directory first, which then replaces the target
+/// file. Thus, the file contains either old or new data even if the process is
+/// interrupted.
+pub fn write_file(path: &Path, data: &[u8]) -> VcsResult<()> {
+    let mut file = AtomicFile::create(path)?;
+    file.write_all(data)
+        .with_context(|| format!("Failed to write to {}", path.display()))?;
+    file.persist(path)
+}
+
+/// A temporary file which replaces the destination file once all the data is
+/// written. The temporary file is removed if it is dropped before that.
+pub struct AtomicFile {
+    temp_path: PathBuf,
+    writer: Option<BufWriter<File>>,
+}
+
+impl AtomicFile {
+    /// Creates a temporary file next to the given path.
+    pub fn create(path: &Path) -> VcsResult<Self> {
+        create_parent(path)?;
+        let temp_path = get_temp_path(path);
+        let file = File::create(&temp_path)
+            .with_context(|| format!("Failed to create {}", temp_path.display()))?;
+        Ok(Self {
+            temp_path,
+            writer: Some(BufWriter::new(file)),
+        })
+    }
+
+    /// Creates a temporary file in the given directory, for data whose
+    /// destination is only known once it is written.
+    pub fn create_in(directory: &Path) -> VcsResult<Self> {
+        fs::create_dir_all(directory)
+            .with_context(|| format!("Failed to create {} directory", directory.display()))?;
+        let temp_path = get_temp_path(&directory.join("file"));
+        let file = File::create(&temp_path)
+            .with_context(|| format!("Failed to create {}", temp_path.display()))?;
+        Ok(Self {
+            temp_path,
+            writer: Some(BufWriter::new(file)),
+        })
+    }
+
+    /// Flushes the data to the disk and moves the file to the given path. The
+    /// path must be on the same filesystem as the one the file was created
+    /// for.
+    pub fn persist(mut self, path: &Path) -> VcsResult<()> {
+        let writer = self.writer.take().unwrap();
+        let result = create_parent(path).and_then(|_| {
+            writer
+                .into_inner()
+                .map_err(|err| err.into_error())
+                .and_then(|file| file.sync_all())
+                .and_then(|_| fs::rename(&self.temp_path, path))
+                .with_context(|| format!("Failed to write to {}", path.display()))
+        });
+        if result.is_err() {
+            let _ = fs::remove_file(&self.temp_path);
+        }
+        result
+    }
+}
+
+impl Write for AtomicFile {
+    fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+        self.writer.as_mut().unwrap().write(buf)
+    }
+
+    fn flush(&mut self) -> std::io::Result<()> {
+        self.writer.as_mut().unwrap().flush()
+    }
+}
+
+impl Drop for AtomicFile {
+    fn drop(&mut self) {
+        if self.writer.take().is_some() {
+            let _ = fs::remove_file(&self.temp_path);
+        }
+    }
+}
+
+fn create_parent(path: &Path) -> VcsResult<()> {
     if let Some(parent) = path.parent() {
         fs::create_dir_all(parent)
             .with_context(|| format!("Failed to create {} directory", parent.display()))?;
     }
-    fs::write(path, data).with_context(|| format!("Failed to write to {}", path.display()))
+    Ok(())
+}
+
+/// Returns the mode of the file without following symbolic links.
+pub fn get_file_mode(path: &Path) -> VcsResult<FileMode> {
+    let metadata = fs::symlink_metadata(path)
+        .with_context(|| format!("Failed to read {} metadata", path.display()))?;
+    if metadata.file_type().is_symlink() {
+        Ok(FileMode::Symlink)
+    } else if is_executable(&metadata) {
+        Ok(FileMode::Executable)
+    } else {
+        Ok(FileMode::Regular)
+    }
+}
+
+/// Returns the file's stat data without following symbolic links.
+pub fn get_file_stat(path: &Path) -> VcsResult<FileStat> {
+    let metadata = fs::symlink_metadata(path)
+        .with_context(|| format!("Failed to read {} metadata", path.display()))?;
+    Ok(make_stat(&metadata))
+}
+
+#[cfg(unix)]
+fn make_stat(metadata: &fs::Metadata) -> FileStat {
+    use std::os::unix::fs::MetadataExt;
+    FileStat {
+        size: metadata.size(),
+        mtime: (metadata.mtime(), metadata.mtime_nsec() as u32),
+        ctime: (metadata.ctime(), metadata.ctime_nsec() as u32),
+        inode: metadata.ino(),
+    }
+}
+
+#[cfg(not(unix))]
+fn make_stat(metadata: &fs::Metadata) -> FileStat {
+    let mtime = metadata
+        .modified()
+        .ok()
+        .and_then(|time| time.duration_since(std::time::UNIX_EPOCH).ok())
+        .map_or((0, 0), |time| (time.as_secs() as i64, time.subsec_nanos()));
+    FileStat {
+        size: metadata.len(),
+        mtime,
+        ctime: (0, 0),
+        inode: 0,
+    }
+}
+
+#[cfg(unix)]
+fn is_executable(metadata: &fs::Metadata) -> bool {
+    use std::os::unix::fs::PermissionsExt;
+    metadata.permissions().mode() & 0o111 != 0
+}
+
+#[cfg(not(unix))]
+fn is_executable(_metadata: &fs::Metadata) -> bool {
+    false
+}
+
+/// Returns true if the path is a directory. Unlike `Path::is_dir`, symbolic
+/// links to directories are not followed.
+pub fn is_dir(path: &Path) -> bool {
+    fs::symlink_metadata(path)
+        .map(|m| m.is_dir())
+        .unwrap_or(false)
+}
+
+/// Opens the file's data the way it is stored in a Blob: the contents of a
+/// regular file or the target of a symbolic link.
+pub fn open_data(path: &Path, mode: FileMode) -> VcsResult<Box<dyn Read>> {
+    match mode {
+        FileMode::Symlink => {
+            let target = fs::read_link(path)
+                .with_context(|| format!("Failed to read link {}", path.display()))?;
+            Ok(Box::new(Cursor::new(raw_path::to_bytes(&target))))
+        }
+        _ => {
+            let file =
+                File::open(path).with_context(|| format!("Failed to read {}", path.display()))?;
+            Ok(Box::new(BufReader::new(file)))
+        }
+    }
+}
+
+/// Writes Blob data to the file with the given mode, replacing whatever the
+/// path contains. A directory is created for `FileMode::Directory`. Regular
+/// files are staged in `temp_dir`, which must be on the same filesystem, so
+/// an interrupted write doesn't leave stray files next to the target.
+pub fn write_data(path: &Path, data: &[u8], mode: FileMode, temp_dir: &Path) -> VcsResult<()> {
+    write_data_with(path, mode, temp_dir, |writer| Ok(writer.write_all(data)?))
+}
+
+/// Same as `write_data`, but the data is streamed by the `write` function.
+/// Regular files are only replaced if `write` succeeds. Symbolic links'
+/// targets are collected in memory.
+pub fn write_data_with(
+    path: &Path,
+    mode: FileMode,
+    temp_dir: &Path,
+    write: impl FnOnce(&mut dyn Write) -> VcsResult<()>,
+) -> VcsResult<()> {
+    match mode {
+        FileMode::Regular | FileMode::Executable => {
+            let mut file = AtomicFile::create_in(temp_dir)?;
+            write(&mut file)?;
+            file.persist(path)?;
+            if mode == FileMode::Executable {
+                set_executable(path)?;
+            }
+            Ok(())
+        }
+        FileMode::Symlink => {
+            let mut target = Vec::new();
+            write(&mut target)?;
+            write_symlink(path, &target)
+        }
+        FileMode::Directory => fs::create_dir_all(path)
+            .with_context(|| format!("Failed to create {} directory", path.display())),
+    }
+}
+
+#[cfg(unix)]
+fn set_executable(path: &Path) -> VcsResult<()> {
+    use std::os::unix::fs::PermissionsExt;
+    let mut permissions = fs::metadata(path)?.permissions();
+    // Grant execution to everyone who can read the file.
+    let mode = permissions.mode();
+    permissions.set_mode(mode | (mode & 0o444) >> 2);
+    fs::set_permissions(path, permissions)
+        .with_context(|| format!("Failed to change {} permissions", path.display()))
+}
+
+#[cfg(not(unix))]
+fn set_executable(_path: &Path) -> VcsResult<()> {
+    Ok(())
+}
+
+/// Creates a symbolic link atomically, the same way `write_file` writes files.
+#[cfg(unix)]
+fn write_symlink(path: &Path, target: &[u8]) -> VcsResult<()> {
+    use std::os::unix::ffi::OsStrExt;
+    create_parent(path)?;
+    let temp_path = get_temp_path(path);
+    let result = std::os::unix::fs::symlink(std::ffi::OsStr::from_bytes(target), &temp_path)
+        .and_then(|_| fs::rename(&temp_path, path));
+    if result.is_err() {
+        let _ = fs::remove_file(&temp_path);
+    }
+    result.with_context(|| format!("Failed to create link {}", path.display()))
+}
+
+/// Symbolic links are checked out as regular files containing the target on
+/// platforms where they can't be created freely.
+#[cfg(not(unix))]
+fn write_symlink(path: &Path, target: &[u8]) -> VcsResult<()> {
+    write_file(path, target)
+}
+
+/// Returns a temporary path next to the given one. The path is unique within
+/// the process, so that the same file can be written from several threads.
+fn get_temp_path(path: &Path) -> PathBuf {
+    static COUNTER: AtomicUsize = AtomicUsize::new(0);
+    let mut file_name = std::ffi::OsString::from(".");
+    file_name.push(path.file_name().unwrap_or_default());
+    file_name.push(format!(
+        ".{}.{}.tmp",
+        std::process::id(),
+        COUNTER.fetch_add(1, Ordering::Relaxed)
+    ));
+    path.with_file_name(file_name)
 }
 
 pub fn is_empty_dir(directory: &Path) -> VcsResult<bool> {
@@ -27,7 +284,7 @@ pub fn is_empty_dir(directory: &Path) -> VcsResult<bool> {
 }
 
 fn is_vcs_directory(entry: &DirEntry) -> bool {
-    entry.path().is_dir()
+    entry.file_type().is_dir()
         && entry
             .file_name()
             .to_str()
@@ -37,7 +294,7 @@ fn is_vcs_directory(entry: &DirEntry) -> bool {
 
 /// Returns a list of all files in the provided diretory ignoring .vcs folder
 pub fn get_all_files(directory: &Path, recursively: bool) -> Vec<PathBuf> {
-    let mut builder = WalkDir::new(directory).min_depth(1);
+    let mut builder = WalkDir::new(directory).min_depth(1).sort_by_file_name();
     if !recursively {
         builder = builder.max_depth(1);
     }
@@ -52,7 +309,7 @@ pub fn get_all_files(directory: &Path, recursively: bool) -> Vec<PathBuf> {
 
 /// Returns a list of all entries in the provided diretory ignoring .vcs folder
 pub fn get_entries(path: &Path, recursively: bool) -> Vec<PathBuf> {
-    let mut builder = WalkDir::new(path).min_depth(1);
+    let mut builder = WalkDir::new(path).min_depth(1).sort_by_file_name();
     if !recursively {
         builder = builder.max_depth(1);
     }
@@ -64,6 +321,18 @@ pub fn get_entries(path: &Path, recursively: bool) -> Vec<PathBuf> {
         .collect()
 }
 
+/// Returns a list of all empty directories in the provided directory ignoring
+/// .vcs folder
+pub fn get_empty_dirs(directory: &Path) -> VcsResult<Vec<PathBuf>> {
+    let mut dirs = Vec::new();
+    for entry in get_entries(directory, true) {
+        if is_dir(&entry) && is_empty_dir(&entry)? {
+            dirs.push(entry);
+        }
+    }
+    Ok(dirs)
+}
+
 pub fn get_relative(parent: &Path, child: &Path) -> PathBuf {
     assert!(parent.is_absolute() && child.is_absolute());
     assert!(child.starts_with(parent));
@@ -76,6 +345,20 @@ mod tests {
     use assert_fs::prelude::*;
     use std::collections::HashSet;
 
+    #[test]
+    fn test_write_file() {
+        let tmp_dir = assert_fs::TempDir::new().unwrap();
+        let path = tmp_dir.child("subdir/file");
+        write_file(&path, b"hello").unwrap();
+        path.assert("hello");
+        write_file(&path, b"goodbye").unwrap();
+        path.assert("goodbye");
+        assert_paths_eq(
+            &get_all_files(tmp_dir.path(), true),
+            &[tmp_dir.join("subdir/file")],
+        );
+    }
+
     #[test]
     fn test_relative_path() {
         let parent = Path::new("/home/username/path/to/repo");
@@ -135,4 +418,32 @@ mod tests {
         let entries = get_all_files(tmp_dir.path(), false);
         assert_paths_eq(&entries, &[tmp_dir.join(".vcs"), tmp_dir.join("file")]);
     }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_write_data() {
+        let tmp_dir = assert_fs::TempDir::new().unwrap();
+        let temp_dir = tmp_dir.join(".vcs/tmp");
+        let script = tmp_dir.join("script.sh");
+        write_data(&script, b"echo hi", FileMode::Executable, &temp_dir).unwrap();
+        assert_eq!(get_file_mode(&script).unwrap(), FileMode::Executable);
+        write_data(&script, b"echo hi", FileMode::Regular, &temp_dir).unwrap();
+        assert_eq!(get_file_mode(&script).unwrap(), FileMode::Regular);
+        assert!(is_empty_dir(&temp_dir).unwrap());
+        assert_eq!(get_all_files(&tmp_dir, false), vec![script.clone()]);
+
+        let link = tmp_dir.join("link");
+        write_data(&link, b"script.sh", FileMode::Symlink, &temp_dir).unwrap();
+        assert_eq!(get_file_mode(&link).unwrap(), FileMode::Symlink);
+        let read_data = |mode| {
+            let mut data = Vec::new();
+            open_data(&link, mode)
+                .unwrap()
+                .read_to_end(&mut data)
+                .unwrap();
+            data
+        };
+        assert_eq!(read_data(FileMode::Symlink), b"script.sh");
+        assert_eq!(read_data(FileMode::Regular), b"echo hi");
+    }
 }
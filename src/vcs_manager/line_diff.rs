@@ -0,0 +1,98 @@
+use similar::{ChangeTag, DiffOp, TextDiff};
+
+/// Size in bytes above which files are not compared line by line, so that
+/// large files are neither loaded in memory nor diffed.
+pub const MAX_DIFF_SIZE: usize = 1024 * 1024;
+
+/// Returns true if the data looks like the contents of a binary file, which is
+/// not compared line by line.
+pub fn is_binary(data: &[u8]) -> bool {
+    data.contains(&0)
+}
+
+/// Counts the lines added to and removed from the old data in the new data.
+pub fn count_changed_lines(old: &[u8], new: &[u8]) -> (usize, usize) {
+    let diff = TextDiff::from_lines(old, new);
+    let (mut added, mut removed) = (0, 0);
+    for change in diff.iter_all_changes() {
+        match change.tag() {
+            ChangeTag::Insert => added += 1,
+            ChangeTag::Delete => removed += 1,
+            ChangeTag::Equal => (),
+        }
+    }
+    (added, removed)
+}
+
+/// Returns the share of the lines of the old and the new data that are kept,
+/// from 0 to 1. Binary data is only similar to the same data.
+pub fn similarity(old: &[u8], new: &[u8]) -> f32 {
+    if old == new {
+        1.0
+    } else if is_binary(old) || is_binary(new) {
+        0.0
+    } else {
+        TextDiff::from_lines(old, new).ratio()
+    }
+}
+
+/// Splits the data into lines, keeping their line endings.
+pub fn split_lines(data: &[u8]) -> Vec<&[u8]> {
+    data.split_inclusive(|&byte| byte == b'\n').collect()
+}
+
+/// Maps each line of the new data to the line of the old data it was kept
+/// from, or to None if it was added or changed.
+pub fn map_kept_lines(old: &[u8], new: &[u8]) -> Vec<Option<usize>> {
+    let diff = TextDiff::from_lines(old, new);
+    let mut lines = vec![None; diff.new_slices().len()];
+    for op in diff.ops() {
+        if let DiffOp::Equal {
+            old_index,
+            new_index,
+            len,
+        } = *op
+        {
+            for offset in 0..len {
+                lines[new_index + offset] = Some(old_index + offset);
+            }
+        }
+    }
+    lines
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_count_changed_lines() {
+        assert_eq!(count_changed_lines(b"", b""), (0, 0));
+        assert_eq!(count_changed_lines(b"", b"a\nb\n"), (2, 0));
+        assert_eq!(count_changed_lines(b"a\nb\nc\n", b"a\nx\nc\n"), (1, 1));
+        assert_eq!(count_changed_lines(b"a\nb", b"a\n"), (0, 1));
+        // The last line changes when a newline is added at its end.
+        assert_eq!(count_changed_lines(b"a", b"a\n"), (1, 1));
+    }
+
+    #[test]
+    fn test_map_kept_lines() {
+        assert_eq!(map_kept_lines(b"", b""), []);
+        assert_eq!(map_kept_lines(b"", b"a\n"), [None]);
+        assert_eq!(
+            map_kept_lines(b"a\nb\nc\n", b"x\na\nc\ny"),
+            [None, Some(0), Some(2), None]
+        );
+        assert_eq!(split_lines(b"x\na\nc\ny").len(), 4);
+        assert_eq!(split_lines(b"a\n").len(), 1);
+    }
+
+    #[test]
+    fn test_similarity() {
+        assert_eq!(similarity(b"\0a", b"\0a"), 1.0);
+        assert_eq!(similarity(b"\0a", b"\0b"), 0.0);
+        assert_eq!(similarity(b"a\nb\n", b"a\nb\n"), 1.0);
+        assert_eq!(similarity(b"a\nb\n", b"a\nc\n"), 0.5);
+        assert_eq!(similarity(b"a\n", b"b\n"), 0.0);
+    }
+}
@@ -0,0 +1,209 @@
+use super::file_manager;
+use super::objects_manager::get_vcs_lock_path;
+use crate::errors::{VcsError, VcsResult};
+
+use anyhow::Context;
+use std::fs;
+use std::io::ErrorKind;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+/// Exclusive lock of a repository held by the current process. The lock is
+/// released when the object is dropped.
+#[derive(Debug)]
+pub struct RepositoryLock {
+    path: PathBuf,
+}
+
+impl RepositoryLock {
+    /// Acquires the lock by creating a lock file containing the current
+    /// process id. Fails if the lock is held by another running process. A
+    /// lock left behind by a process that is no longer running is removed.
+    pub fn acquire(repo: &Path) -> VcsResult<Self> {
+        let path = get_vcs_lock_path(repo);
+        // The pid is written to a temporary file first and then linked to the
+        // lock path, so that the lock file never exists without the pid.
+        let temp_path = get_temp_path(&path, "lock");
+        file_manager::write_file(&temp_path, std::process::id().to_string().as_bytes())?;
+        let result = Self::link(&temp_path, &path);
+        fs::remove_file(&temp_path)?;
+        result?;
+        Ok(Self { path })
+    }
+
+    fn link(temp_path: &Path, path: &Path) -> VcsResult<()> {
+        loop {
+            match fs::hard_link(temp_path, path) {
+                Ok(()) => return Ok(()),
+                Err(err) if err.kind() == ErrorKind::AlreadyExists => {
+                    let Some(owner) = read_owner(path) else {
+                        continue;
+                    };
+                    if let Some(pid) = owner.filter(|pid| is_running(*pid)) {
+                        Err(VcsError::RepositoryLocked(pid))?;
+                    }
+                    Self::take_over(path, owner)?;
+                }
+                Err(err) => {
+                    return Err(err).with_context(|| format!("Failed to create {}", path.display()))
+                }
+            }
+        }
+    }
+
+    /// Removes the stale lock left by `owner`. The lock is first moved aside,
+    /// so that of several processes finding the same stale lock only one
+    /// removes it. If the lock moved aside is not the stale one anymore,
+    /// another process took the lock over in the meantime and it is put back.
+    fn take_over(path: &Path, owner: Option<u32>) -> VcsResult<()> {
+        let stale_path = get_temp_path(path, "lock.stale");
+        match fs::rename(path, &stale_path) {
+            Err(err) if err.kind() == ErrorKind::NotFound => return Ok(()),
+            result => result?,
+        }
+        let moved_owner = read_owner(&stale_path).flatten();
+        if moved_owner == owner || moved_owner.is_none() {
+            fs::remove_file(&stale_path)?;
+            return Ok(());
+        }
+        let restored = fs::hard_link(&stale_path, path);
+        fs::remove_file(&stale_path)?;
+        match restored {
+            Err(err) if err.kind() != ErrorKind::AlreadyExists => Err(err)?,
+            _ => Err(VcsError::RepositoryLocked(moved_owner.unwrap()))?,
+        }
+    }
+}
+
+/// Returns a path next to the lock file starting with `prefix`. The path is
+/// unique to the process and the call, so that neither other processes nor
+/// other threads acquiring the lock at the same time use it.
+fn get_temp_path(path: &Path, prefix: &str) -> PathBuf {
+    static COUNTER: AtomicUsize = AtomicUsize::new(0);
+    path.with_file_name(format!(
+        "{prefix}.{}.{}",
+        std::process::id(),
+        COUNTER.fetch_add(1, Ordering::Relaxed)
+    ))
+}
+
+/// Reads the process id from a lock file. Returns `None` if the file doesn't
+/// exist anymore and `Some(None)` if it doesn't contain a process id.
+fn read_owner(path: &Path) -> Option<Option<u32>> {
+    match fs::read_to_string(path) {
+        Ok(pid) => Some(pid.trim().parse().ok()),
+        Err(err) if err.kind() == ErrorKind::NotFound => None,
+        Err(_) => Some(None),
+    }
+}
+
+impl Drop for RepositoryLock {
+    fn drop(&mut self) {
+        let _ = fs::remove_file(&self.path);
+    }
+}
+
+#[cfg(unix)]
+fn is_running(pid: u32) -> bool {
+    let Ok(pid) = libc::pid_t::try_from(pid) else {
+        return false;
+    };
+    // Signal 0 only checks whether the process exists and can be signalled.
+    let result = unsafe { libc::kill(pid, 0) };
+    result == 0 || std::io::Error::last_os_error().raw_os_error() == Some(libc::EPERM)
+}
+
+#[cfg(not(unix))]
+fn is_running(_pid: u32) -> bool {
+    true
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use assert_fs::prelude::*;
+
+    fn get_dead_pid() -> u32 {
+        let mut child = std::process::Command::new("true").spawn().unwrap();
+        child.wait().unwrap();
+        child.id()
+    }
+
+    #[test]
+    fn test_lock() {
+        let tmp_dir = assert_fs::TempDir::new().unwrap();
+        tmp_dir.child(".vcs").create_dir_all().unwrap();
+        let lock = RepositoryLock::acquire(tmp_dir.path()).unwrap();
+        tmp_dir
+            .child(".vcs/lock")
+            .assert(std::process::id().to_string());
+
+        let err = RepositoryLock::acquire(tmp_dir.path()).unwrap_err();
+        assert!(matches!(
+            err.downcast_ref::<VcsError>(),
+            Some(VcsError::RepositoryLocked(_))
+        ));
+
+        drop(lock);
+        tmp_dir
+            .child(".vcs/lock")
+            .assert(predicates::path::missing());
+        assert!(RepositoryLock::acquire(tmp_dir.path()).is_ok());
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_stale_lock() {
+        let tmp_dir = assert_fs::TempDir::new().unwrap();
+        let lock_file = tmp_dir.child(".vcs/lock");
+        lock_file.write_str(&get_dead_pid().to_string()).unwrap();
+        let _lock = RepositoryLock::acquire(tmp_dir.path()).unwrap();
+        lock_file.assert(std::process::id().to_string());
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_stale_lock_taken_over() {
+        let tmp_dir = assert_fs::TempDir::new().unwrap();
+        let lock_file = tmp_dir.child(".vcs/lock");
+        let dead_pid = get_dead_pid();
+        lock_file
+            .write_str(&std::process::id().to_string())
+            .unwrap();
+
+        // Another process replaced the stale lock after it was found stale.
+        let err = RepositoryLock::take_over(lock_file.path(), Some(dead_pid)).unwrap_err();
+        assert!(matches!(
+            err.downcast_ref::<VcsError>(),
+            Some(VcsError::RepositoryLocked(_))
+        ));
+        lock_file.assert(std::process::id().to_string());
+        let entries: Vec<_> = fs::read_dir(tmp_dir.join(".vcs"))
+            .unwrap()
+            .map(|entry| entry.unwrap().file_name())
+            .collect();
+        assert_eq!(entries, ["lock"]);
+    }
+
+    #[test]
+    fn test_concurrent_acquire() {
+        let tmp_dir = assert_fs::TempDir::new().unwrap();
+        let results: Vec<_> = std::thread::scope(|scope| {
+            let handles: Vec<_> = (0..8)
+                .map(|_| scope.spawn(|| RepositoryLock::acquire(tmp_dir.path())))
+                .collect();
+            handles.into_iter().map(|h| h.join().unwrap()).collect()
+        });
+        let mut acquired = 0;
+        for result in &results {
+            match result {
+                Ok(_) => acquired += 1,
+                Err(err) => assert!(matches!(
+                    err.downcast_ref::<VcsError>(),
+                    Some(VcsError::RepositoryLocked(_))
+                )),
+            }
+        }
+        assert_eq!(acquired, 1);
+    }
+}
@@ -0,0 +1,117 @@
+mod database;
+mod loose;
+#[cfg(test)]
+mod memory;
+
+pub use self::database::DatabaseStore;
+pub use self::loose::LooseStore;
+#[cfg(test)]
+pub use self::memory::MemoryStore;
+
+use super::objects::{Bytes, ObjectStoreKind, VcsObjectId};
+use crate::errors::VcsResult;
+
+use std::fmt;
+use std::io::{Cursor, Read, Write};
+use std::path::{Path, PathBuf};
+use std::time::SystemTime;
+
+/// Storage of serialized objects addressed by their ids. Stores are shared
+/// between threads, so that objects can be read and written in parallel.
+pub trait ObjectStore: fmt::Debug + Send + Sync {
+    /// Returns the serialized object or None if there is no such object.
+    fn get(&self, id: &VcsObjectId) -> VcsResult<Option<Bytes>>;
+
+    /// Stores the serialized object under the id. It is only kept for good
+    /// once the store is flushed.
+    fn put(&self, id: &VcsObjectId, content: &[u8]) -> VcsResult<()>;
+
+    fn contains(&self, id: &VcsObjectId) -> VcsResult<bool>;
+
+    /// Lists everything in the store, including the entries that are not valid
+    /// objects.
+    fn iter(&self) -> VcsResult<Box<dyn Iterator<Item = StoredObject> + '_>>;
+
+    /// Removes the objects. Missing objects are ignored.
+    fn remove(&self, ids: &[VcsObjectId]) -> VcsResult<()>;
+
+    /// Returns the path the object is stored at, for messages.
+    fn location(&self, id: &VcsObjectId) -> PathBuf;
+
+    /// Opens the serialized object for reading. Returns None if there is no
+    /// such object. Stores that can read objects in pieces override it, so
+    /// that large objects are not loaded in memory.
+    fn open(&self, id: &VcsObjectId) -> VcsResult<Option<Box<dyn Read + '_>>> {
+        Ok(self
+            .get(id)?
+            .map(|content| Box::new(Cursor::new(content)) as Box<dyn Read>))
+    }
+
+    /// Writes the objects stored so far for good. Stores that batch writes
+    /// keep objects in memory until then. It must be called before anything
+    /// outside the store refers to the objects.
+    fn flush(&self) -> VcsResult<()> {
+        Ok(())
+    }
+
+    /// Keeps what the store reads objects from open until the session is
+    /// dropped, so that the operations of a command don't reopen it each time.
+    /// Stores that keep nothing open return an empty session.
+    fn session(&self) -> VcsResult<StoreSession> {
+        Ok(StoreSession::default())
+    }
+
+    /// Returns the size of the largest file the store can keep as a single
+    /// blob without loading it in memory, or None if objects of any size are
+    /// streamed. Larger files are stored as chunks.
+    fn max_blob_size(&self) -> Option<u64> {
+        None
+    }
+
+    /// Stores the object `write` serializes and returns its id, which `write`
+    /// computes while serializing. Stores that can write objects in pieces
+    /// override it, so that large objects are not kept in memory.
+    fn put_with(
+        &self,
+        write: &mut dyn FnMut(&mut dyn Write) -> VcsResult<VcsObjectId>,
+    ) -> VcsResult<VcsObjectId> {
+        let mut content = Vec::new();
+        let id = write(&mut content)?;
+        self.put(&id, &content)?;
+        Ok(id)
+    }
+}
+
+/// Resources an object store keeps open for a series of operations. They are
+/// released when the session is dropped.
+#[derive(Default)]
+pub struct StoreSession {
+    _resources: Option<Box<dyn Send + Sync>>,
+}
+
+impl StoreSession {
+    pub fn new(resources: impl Send + Sync + 'static) -> Self {
+        Self {
+            _resources: Some(Box::new(resources)),
+        }
+    }
+}
+
+/// An entry of an object store.
+#[derive(Debug, Clone)]
+pub struct StoredObject {
+    /// None if the entry is not a valid object, e.g. a stray file.
+    pub id: Option<VcsObjectId>,
+    /// Where the entry is stored, see `ObjectStore::location`.
+    pub location: PathBuf,
+    pub size: u64,
+    pub modified: SystemTime,
+}
+
+/// Opens the object store of the given kind of the repository.
+pub fn open_store(repo: &Path, kind: ObjectStoreKind) -> VcsResult<Box<dyn ObjectStore>> {
+    Ok(match kind {
+        ObjectStoreKind::Loose => Box::new(LooseStore::new(repo)),
+        ObjectStoreKind::Database => Box::new(DatabaseStore::open(repo)?),
+    })
+}
@@ -0,0 +1,221 @@
+use super::{ObjectStore, StoreSession, StoredObject};
+use crate::errors::VcsResult;
+use crate::vcs_manager::objects::{Bytes, VcsObjectId};
+use crate::vcs_manager::objects_manager::get_vcs_database_path;
+use crate::vcs_manager::public_info::get_human_id;
+
+use anyhow::Context;
+use redb::{Database, DatabaseError, ReadableTable, TableDefinition};
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex, Weak};
+use std::thread;
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
+
+/// Objects keyed by their ids. Values are the time the object was stored, in
+/// seconds since the epoch, and the serialized object.
+const OBJECTS: TableDefinition<&[u8], (u64, &[u8])> = TableDefinition::new("objects");
+
+/// How long to wait for another process to close the database.
+const OPEN_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// Size of the largest file stored as a single blob. Objects are read and
+/// written whole, so larger files are always stored as chunks, whatever the
+/// chunk threshold is.
+const MAX_BLOB_SIZE: u64 = 8 * 1024 * 1024;
+
+/// Size of the objects kept in memory after which they are written even if
+/// the store isn't flushed yet.
+const MAX_PENDING_SIZE: usize = 64 * 1024 * 1024;
+
+/// The databases open in this process by their paths. A database file can only
+/// be opened once at a time, so all the stores of a repository share it.
+static OPEN_DATABASES: Mutex<BTreeMap<PathBuf, Weak<Database>>> = Mutex::new(BTreeMap::new());
+
+/// Stores all objects in a single embedded database file. The database is only
+/// kept open while an operation or a session uses it, so that other processes
+/// can use it in between. Opening it waits for the process that has it open.
+/// Objects are read and written whole, so large files are stored as chunks (see
+/// `MAX_BLOB_SIZE`). Stored objects are kept in memory and written together in
+/// one transaction when the store is flushed.
+pub struct DatabaseStore {
+    path: PathBuf,
+    pending: Mutex<PendingObjects>,
+}
+
+/// Objects stored but not written to the database yet.
+#[derive(Default)]
+struct PendingObjects {
+    objects: BTreeMap<VcsObjectId, Bytes>,
+    size: usize,
+}
+
+impl DatabaseStore {
+    /// Opens the repository's database, creating it if needed.
+    pub fn open(repo: &Path) -> VcsResult<Self> {
+        let path = get_vcs_database_path(repo);
+        let is_new = !path.exists();
+        let store = Self {
+            path,
+            pending: Mutex::default(),
+        };
+        if is_new {
+            let database = store.database()?;
+            let transaction = database.begin_write()?;
+            transaction.open_table(OBJECTS)?;
+            transaction.commit()?;
+        }
+        Ok(store)
+    }
+
+    /// Returns the database, opening it unless an operation or a session of
+    /// this process has it open already. It is closed when the last one using
+    /// it drops it.
+    fn database(&self) -> VcsResult<Arc<Database>> {
+        let mut databases = OPEN_DATABASES.lock().unwrap();
+        if let Some(database) = databases.get(&self.path).and_then(Weak::upgrade) {
+            return Ok(database);
+        }
+        let started = Instant::now();
+        let database = loop {
+            match Database::create(&self.path) {
+                Ok(database) => break Arc::new(database),
+                Err(DatabaseError::DatabaseAlreadyOpen) if started.elapsed() < OPEN_TIMEOUT => {
+                    thread::sleep(Duration::from_millis(10))
+                }
+                Err(err) => {
+                    return Err(err)
+                        .with_context(|| format!("Failed to open {}", self.path.display()))
+                }
+            }
+        };
+        databases.retain(|_, database| database.strong_count() > 0);
+        databases.insert(self.path.clone(), Arc::downgrade(&database));
+        Ok(database)
+    }
+
+    /// Writes the pending objects in one transaction. The pending objects stay
+    /// locked meanwhile, so that they can't be looked for in between.
+    fn write_pending(&self, pending: &mut PendingObjects) -> VcsResult<()> {
+        if pending.objects.is_empty() {
+            return Ok(());
+        }
+        let modified = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .unwrap_or_default()
+            .as_secs();
+        let database = self.database()?;
+        let transaction = database.begin_write()?;
+        {
+            let mut table = transaction.open_table(OBJECTS)?;
+            for (id, content) in pending.objects.iter() {
+                // Objects never change, so an object stored already is left as
+                // it is.
+                if table.get(id.as_bytes())?.is_none() {
+                    table.insert(id.as_bytes(), (modified, content.as_slice()))?;
+                }
+            }
+        }
+        transaction.commit()?;
+        *pending = PendingObjects::default();
+        Ok(())
+    }
+}
+
+impl std::fmt::Debug for DatabaseStore {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("DatabaseStore")
+            .field("path", &self.path)
+            .finish()
+    }
+}
+
+impl ObjectStore for DatabaseStore {
+    fn get(&self, id: &VcsObjectId) -> VcsResult<Option<Bytes>> {
+        if let Some(content) = self.pending.lock().unwrap().objects.get(id) {
+            return Ok(Some(content.clone()));
+        }
+        let database = self.database()?;
+        let transaction = database.begin_read()?;
+        let table = transaction.open_table(OBJECTS)?;
+        let value = table.get(id.as_bytes())?;
+        Ok(value.map(|value| value.value().1.to_vec()))
+    }
+
+    fn put(&self, id: &VcsObjectId, content: &[u8]) -> VcsResult<()> {
+        if self.contains(id)? {
+            return Ok(());
+        }
+        let mut pending = self.pending.lock().unwrap();
+        if pending.objects.insert(*id, content.to_vec()).is_none() {
+            pending.size += content.len();
+        }
+        if pending.size > MAX_PENDING_SIZE {
+            self.write_pending(&mut pending)?;
+        }
+        Ok(())
+    }
+
+    fn max_blob_size(&self) -> Option<u64> {
+        Some(MAX_BLOB_SIZE)
+    }
+
+    fn contains(&self, id: &VcsObjectId) -> VcsResult<bool> {
+        if self.pending.lock().unwrap().objects.contains_key(id) {
+            return Ok(true);
+        }
+        let database = self.database()?;
+        let transaction = database.begin_read()?;
+        let table = transaction.open_table(OBJECTS)?;
+        Ok(table.get(id.as_bytes())?.is_some())
+    }
+
+    fn iter(&self) -> VcsResult<Box<dyn Iterator<Item = StoredObject> + '_>> {
+        self.flush()?;
+        let database = self.database()?;
+        let transaction = database.begin_read()?;
+        let table = transaction.open_table(OBJECTS)?;
+        let mut objects = Vec::new();
+        for entry in table.iter()? {
+            let (key, value) = entry?;
+            let (modified, content) = value.value();
+            let id = VcsObjectId::from_bytes(key.value());
+            objects.push(StoredObject {
+                location: match &id {
+                    Some(id) => self.location(id),
+                    None => self.path.join(hex::encode(key.value())),
+                },
+                id,
+                size: content.len() as u64,
+                modified: UNIX_EPOCH + Duration::from_secs(modified),
+            });
+        }
+        Ok(Box::new(objects.into_iter()))
+    }
+
+    fn remove(&self, ids: &[VcsObjectId]) -> VcsResult<()> {
+        self.flush()?;
+        let database = self.database()?;
+        let transaction = database.begin_write()?;
+        {
+            let mut table = transaction.open_table(OBJECTS)?;
+            for id in ids {
+                table.remove(id.as_bytes())?;
+            }
+        }
+        transaction.commit()?;
+        Ok(())
+    }
+
+    fn location(&self, id: &VcsObjectId) -> PathBuf {
+        self.path.join(get_human_id(id))
+    }
+
+    fn flush(&self) -> VcsResult<()> {
+        self.write_pending(&mut self.pending.lock().unwrap())
+    }
+
+    fn session(&self) -> VcsResult<StoreSession> {
+        Ok(StoreSession::new(self.database()?))
+    }
+}
@@ -0,0 +1,124 @@
+use super::{ObjectStore, StoredObject};
+use crate::errors::VcsResult;
+use crate::vcs_manager::file_manager::{self, AtomicFile};
+use crate::vcs_manager::objects::{Bytes, VcsObjectId};
+use crate::vcs_manager::objects_manager::{
+    get_vcs_objects_path, get_vcs_tmp_path, get_vsc_object_path,
+};
+use crate::vcs_manager::public_info::get_inner_id;
+
+use anyhow::Context;
+use std::fs;
+use std::io::{ErrorKind, Read, Write};
+use std::path::{Path, PathBuf};
+
+/// Stores every object in its own file named after its id, in a subdirectory
+/// named after the first two digits of the id. Objects are written to
+/// temporary files in `.vcs/tmp` first, so that a write that didn't complete
+/// leaves nothing behind in the objects directory.
+#[derive(Debug)]
+pub struct LooseStore {
+    repo: PathBuf,
+}
+
+impl LooseStore {
+    pub fn new(repo: &Path) -> Self {
+        Self {
+            repo: repo.to_owned(),
+        }
+    }
+
+    fn open_file(&self, id: &VcsObjectId) -> VcsResult<Option<fs::File>> {
+        let path = self.location(id);
+        match fs::File::open(&path) {
+            Ok(file) => Ok(Some(file)),
+            Err(err) if err.kind() == ErrorKind::NotFound => Ok(None),
+            Err(err) => Err(err).with_context(|| format!("Failed to read {}", path.display())),
+        }
+    }
+}
+
+impl ObjectStore for LooseStore {
+    fn get(&self, id: &VcsObjectId) -> VcsResult<Option<Bytes>> {
+        let Some(mut file) = self.open_file(id)? else {
+            return Ok(None);
+        };
+        let mut content = Vec::new();
+        file.read_to_end(&mut content)?;
+        Ok(Some(content))
+    }
+
+    fn put(&self, id: &VcsObjectId, content: &[u8]) -> VcsResult<()> {
+        let path = self.location(id);
+        let mut file = AtomicFile::create_in(&get_vcs_tmp_path(&self.repo))?;
+        file.write_all(content)
+            .with_context(|| format!("Failed to write to {}", path.display()))?;
+        file.persist(&path)
+    }
+
+    fn contains(&self, id: &VcsObjectId) -> VcsResult<bool> {
+        Ok(self.location(id).is_file())
+    }
+
+    fn iter(&self) -> VcsResult<Box<dyn Iterator<Item = StoredObject> + '_>> {
+        let objects_path = get_vcs_objects_path(&self.repo);
+        let paths = file_manager::get_all_files(&objects_path, true);
+        let mut objects = Vec::with_capacity(paths.len());
+        for path in paths {
+            let human_id: String = file_manager::get_relative(&objects_path, &path)
+                .iter()
+                .map(|part| part.to_string_lossy())
+                .collect();
+            let id = get_inner_id(&human_id)
+                .ok()
+                .filter(|id| self.location(id) == path);
+            let metadata = fs::metadata(&path)?;
+            objects.push(StoredObject {
+                id,
+                location: path,
+                size: metadata.len(),
+                modified: metadata.modified()?,
+            });
+        }
+        Ok(Box::new(objects.into_iter()))
+    }
+
+    fn remove(&self, ids: &[VcsObjectId]) -> VcsResult<()> {
+        for id in ids {
+            let path = self.location(id);
+            match fs::remove_file(&path) {
+                Ok(()) => (),
+                Err(err) if err.kind() == ErrorKind::NotFound => continue,
+                Err(err) => return Err(err.into()),
+            }
+            if let Some(parent) = path.parent() {
+                if file_manager::is_empty_dir(parent)? {
+                    fs::remove_dir(parent)?;
+                }
+            }
+        }
+        Ok(())
+    }
+
+    fn location(&self, id: &VcsObjectId) -> PathBuf {
+        get_vsc_object_path(&self.repo, id)
+    }
+
+    fn open(&self, id: &VcsObjectId) -> VcsResult<Option<Box<dyn Read + '_>>> {
+        Ok(self
+            .open_file(id)?
+            .map(|file| Box::new(file) as Box<dyn Read>))
+    }
+
+    fn put_with(
+        &self,
+        write: &mut dyn FnMut(&mut dyn Write) -> VcsResult<VcsObjectId>,
+    ) -> VcsResult<VcsObjectId> {
+        // The object is written to a temporary file, as its id, and so its
+        // path, is only known once it is written.
+        let mut file = AtomicFile::create_in(&get_vcs_tmp_path(&self.repo))?;
+        let id = write(&mut file)?;
+        file.persist(&self.location(&id))?;
+        Ok(id)
+    }
+}
@@ -0,0 +1,65 @@
+use super::{ObjectStore, StoredObject};
+use crate::errors::VcsResult;
+use crate::vcs_manager::objects::{Bytes, VcsObjectId};
+use crate::vcs_manager::public_info::get_human_id;
+
+use std::collections::BTreeMap;
+use std::path::PathBuf;
+use std::sync::Mutex;
+use std::time::SystemTime;
+
+/// Keeps objects in memory. Nothing is persisted, so it is only meant for
+/// tests.
+#[derive(Debug, Default)]
+pub struct MemoryStore {
+    objects: Mutex<BTreeMap<VcsObjectId, (Bytes, SystemTime)>>,
+}
+
+impl MemoryStore {
+    pub fn new() -> Self {
+        Self::default()
+    }
+}
+
+impl ObjectStore for MemoryStore {
+    fn get(&self, id: &VcsObjectId) -> VcsResult<Option<Bytes>> {
+        let objects = self.objects.lock().unwrap();
+        Ok(objects.get(id).map(|(content, _)| content.clone()))
+    }
+
+    fn put(&self, id: &VcsObjectId, content: &[u8]) -> VcsResult<()> {
+        let mut objects = self.objects.lock().unwrap();
+        objects.insert(*id, (content.to_vec(), SystemTime::now()));
+        Ok(())
+    }
+
+    fn contains(&self, id: &VcsObjectId) -> VcsResult<bool> {
+        Ok(self.objects.lock().unwrap().contains_key(id))
+    }
+
+    fn iter(&self) -> VcsResult<Box<dyn Iterator<Item = StoredObject> + '_>> {
+        let objects = self.objects.lock().unwrap();
+        let objects: Vec<_> = objects
+            .iter()
+            .map(|(id, (content, modified))| StoredObject {
+                id: Some(*id),
+                location: self.location(id),
+                size: content.len() as u64,
+                modified: *modified,
+            })
+            .collect();
+        Ok(Box::new(objects.into_iter()))
+    }
+
+    fn remove(&self, ids: &[VcsObjectId]) -> VcsResult<()> {
+        let mut objects = self.objects.lock().unwrap();
+        for id in ids {
+            objects.remove(id);
+        }
+        Ok(())
+    }
+
+    fn location(&self, id: &VcsObjectId) -> PathBuf {
+        PathBuf::from(get_human_id(id))
+    }
+}
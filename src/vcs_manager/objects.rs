@@ -1,53 +1,344 @@
+use super::public_info::get_human_id;
 use super::traits::VcsSerialize;
+use crate::errors::VcsError;
 
+use chrono::format::{Item, StrftimeItems};
+use chrono::{DateTime, Local};
+use rayon::prelude::*;
 use serde::{Deserialize, Serialize};
 use sha1::{Digest, Sha1};
+use sha2::Sha256;
 use std::collections::BTreeMap;
 use std::path::{Path, PathBuf};
 use std::time::SystemTime;
 
-/// Vcs object unique id.
-pub type VcsObjectId = [u8; 20];
+/// Vcs object unique id: a SHA-1 or a SHA-256 digest of the object's
+/// contents, depending on the repository's hash algorithm.
+#[derive(Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct VcsObjectId {
+    len: u8,
+    bytes: [u8; MAX_ID_LEN],
+}
+
+const MAX_ID_LEN: usize = 32;
+
+impl VcsObjectId {
+    /// Creates an id from a digest. Returns None unless the digest is a SHA-1
+    /// or a SHA-256 one.
+    pub fn from_bytes(digest: &[u8]) -> Option<Self> {
+        if digest.len() != HashAlgorithm::Sha1.id_len()
+            && digest.len() != HashAlgorithm::Sha256.id_len()
+        {
+            return None;
+        }
+        let mut bytes = [0; MAX_ID_LEN];
+        bytes[..digest.len()].copy_from_slice(digest);
+        Some(Self {
+            len: digest.len() as u8,
+            bytes,
+        })
+    }
+    /// Returns the digest.
+    pub fn as_bytes(&self) -> &[u8] {
+        &self.bytes[..self.len as usize]
+    }
+}
+
+impl AsRef<[u8]> for VcsObjectId {
+    fn as_ref(&self) -> &[u8] {
+        self.as_bytes()
+    }
+}
+
+impl std::fmt::Debug for VcsObjectId {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", hex::encode(self))
+    }
+}
+
+// Ids are stored as arrays of numbers, as SHA-1 ids used to be stored.
+impl Serialize for VcsObjectId {
+    fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.collect_seq(self.as_bytes())
+    }
+}
+
+impl<'de> Deserialize<'de> for VcsObjectId {
+    fn deserialize<D: serde::Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let bytes = Vec::<u8>::deserialize(deserializer)?;
+        Self::from_bytes(&bytes).ok_or_else(|| {
+            serde::de::Error::invalid_length(bytes.len(), &"a SHA-1 or a SHA-256 digest")
+        })
+    }
+}
+
+/// Hash algorithm used to compute object ids. It is chosen once when a
+/// repository is created.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum HashAlgorithm {
+    #[default]
+    Sha1,
+    Sha256,
+}
+
+impl HashAlgorithm {
+    /// Returns the length of the ids produced by the algorithm in bytes.
+    pub fn id_len(&self) -> usize {
+        match self {
+            HashAlgorithm::Sha1 => 20,
+            HashAlgorithm::Sha256 => 32,
+        }
+    }
+}
+
+impl std::str::FromStr for HashAlgorithm {
+    type Err = String;
+
+    fn from_str(name: &str) -> Result<Self, Self::Err> {
+        match name {
+            "sha1" => Ok(HashAlgorithm::Sha1),
+            "sha256" => Ok(HashAlgorithm::Sha256),
+            _ => Err(format!(
+                "unknown hash algorithm {name:?}, expected sha1 or sha256"
+            )),
+        }
+    }
+}
 
-pub fn get_vcs_object_id(content: &Bytes) -> VcsObjectId {
-    let mut hasher = Sha1::new();
+/// Where a repository keeps its objects. It is chosen once when a repository
+/// is created.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ObjectStoreKind {
+    /// A file per object in .vcs/objects.json.
+    #[default]
+    Loose,
+    /// A single database file, .vcs/objects.db.
+    Database,
+}
+
+impl std::str::FromStr for ObjectStoreKind {
+    type Err = String;
+
+    fn from_str(name: &str) -> Result<Self, Self::Err> {
+        match name {
+            "loose" => Ok(ObjectStoreKind::Loose),
+            "database" => Ok(ObjectStoreKind::Database),
+            _ => Err(format!(
+                "unknown object store {name:?}, expected loose or database"
+            )),
+        }
+    }
+}
+
+/// How dates are printed: the default format, one of the named formats or a
+/// custom strftime format given as `format:<format>`.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(try_from = "String", into = "String")]
+pub enum DateFormat {
+    /// Like `Sun Oct 18 22:45:28 2026 +0000`.
+    #[default]
+    Default,
+    /// Like `2026-10-18 22:45:28 +0000`.
+    Iso,
+    /// Like `Sun, 18 Oct 2026 22:45:28 +0000`.
+    Rfc,
+    /// Like `2026-10-18`.
+    Short,
+    Custom(String),
+}
+
+impl DateFormat {
+    /// Formats the time in the local time zone.
+    pub fn format_time(&self, time: &DateTime<Local>) -> String {
+        let format = match self {
+            DateFormat::Default => "%a %b %e %H:%M:%S %Y %z",
+            DateFormat::Iso => "%Y-%m-%d %H:%M:%S %z",
+            DateFormat::Rfc => "%a, %-d %b %Y %H:%M:%S %z",
+            DateFormat::Short => "%Y-%m-%d",
+            DateFormat::Custom(format) => format,
+        };
+        time.format(format).to_string()
+    }
+}
+
+impl std::str::FromStr for DateFormat {
+    type Err = String;
+
+    fn from_str(name: &str) -> Result<Self, Self::Err> {
+        match name {
+            "default" => Ok(DateFormat::Default),
+            "iso" => Ok(DateFormat::Iso),
+            "rfc" => Ok(DateFormat::Rfc),
+            "short" => Ok(DateFormat::Short),
+            _ => match name.strip_prefix("format:") {
+                // Invalid formats would make formatting panic.
+                Some(format) if StrftimeItems::new(format).all(|item| item != Item::Error) => {
+                    Ok(DateFormat::Custom(format.to_owned()))
+                }
+                Some(format) => Err(format!("invalid date format {format:?}")),
+                None => Err(format!(
+                    "unknown date format {name:?}, expected default, iso, rfc, short or format:<format>"
+                )),
+            },
+        }
+    }
+}
+
+impl TryFrom<String> for DateFormat {
+    type Error = String;
+
+    fn try_from(name: String) -> Result<Self, Self::Error> {
+        name.parse()
+    }
+}
+
+impl From<DateFormat> for String {
+    fn from(format: DateFormat) -> Self {
+        match format {
+            DateFormat::Default => "default".to_owned(),
+            DateFormat::Iso => "iso".to_owned(),
+            DateFormat::Rfc => "rfc".to_owned(),
+            DateFormat::Short => "short".to_owned(),
+            DateFormat::Custom(format) => format!("format:{format}"),
+        }
+    }
+}
+
+pub fn get_vcs_object_id(content: &Bytes, algorithm: HashAlgorithm) -> VcsObjectId {
+    let mut hasher = ObjectHasher::new(algorithm);
     hasher.update(content);
-    hasher.finalize().try_into().unwrap()
+    hasher.finish()
+}
+
+/// Computes an object id from contents fed in pieces.
+pub enum ObjectHasher {
+    Sha1(Sha1),
+    Sha256(Sha256),
+}
+impl ObjectHasher {
+    pub fn new(algorithm: HashAlgorithm) -> Self {
+        match algorithm {
+            HashAlgorithm::Sha1 => Self::Sha1(Sha1::new()),
+            HashAlgorithm::Sha256 => Self::Sha256(Sha256::new()),
+        }
+    }
+    pub fn update(&mut self, data: &[u8]) {
+        match self {
+            Self::Sha1(hasher) => hasher.update(data),
+            Self::Sha256(hasher) => hasher.update(data),
+        }
+    }
+    pub fn finish(self) -> VcsObjectId {
+        let digest = match self {
+            Self::Sha1(hasher) => hasher.finalize().to_vec(),
+            Self::Sha256(hasher) => hasher.finalize().to_vec(),
+        };
+        VcsObjectId::from_bytes(&digest).unwrap()
+    }
 }
 
 /// Inner representation of all Vcs structures and objects.
 pub type Bytes = Vec<u8>;
 
 /// Enum whose variants are all possible types of objects in Vcs.
-#[derive(Debug, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub enum VcsObjects {
     Commit(Commit),
     Blob(Blob),
     Tree(Tree),
+    Chunk(Chunk),
+    ChunkList(ChunkList),
 }
 impl VcsSerialize for VcsObjects {}
 impl VcsObjects {
-    pub fn commit(self) -> Commit {
-        if let VcsObjects::Commit(commit) = self {
-            commit
-        } else {
-            panic!("Not a Commit object")
+    /// Returns the name of the object's type.
+    pub fn kind(&self) -> &'static str {
+        match self {
+            VcsObjects::Commit(_) => "commit",
+            VcsObjects::Blob(_) => "blob",
+            VcsObjects::Tree(_) => "tree",
+            VcsObjects::Chunk(_) => "chunk",
+            VcsObjects::ChunkList(_) => "chunklist",
+        }
+    }
+    /// Returns the Commit object or fails if the object is of another type.
+    pub fn commit(self, id: &VcsObjectId) -> Result<Commit, VcsError> {
+        match self {
+            VcsObjects::Commit(commit) => Ok(commit),
+            object => Err(object.wrong_type("commit", id)),
+        }
+    }
+    /// Returns the Tree object or fails if the object is of another type.
+    pub fn tree(self, id: &VcsObjectId) -> Result<Tree, VcsError> {
+        match self {
+            VcsObjects::Tree(tree) => Ok(tree),
+            object => Err(object.wrong_type("tree", id)),
         }
     }
-    pub fn blob(self) -> Blob {
-        if let VcsObjects::Blob(blob) = self {
-            blob
-        } else {
-            panic!("Not a Blob object")
+    /// Returns the Chunk object or fails if the object is of another type.
+    pub fn chunk(self, id: &VcsObjectId) -> Result<Chunk, VcsError> {
+        match self {
+            VcsObjects::Chunk(chunk) => Ok(chunk),
+            object => Err(object.wrong_type("chunk", id)),
         }
     }
-    pub fn tree(self) -> Tree {
-        if let VcsObjects::Tree(tree) = self {
-            tree
-        } else {
-            panic!("Not a Tree object")
+    /// Returns the ChunkList object or fails if the object is of another type.
+    pub fn chunk_list(self, id: &VcsObjectId) -> Result<ChunkList, VcsError> {
+        match self {
+            VcsObjects::ChunkList(chunk_list) => Ok(chunk_list),
+            object => Err(object.wrong_type("chunklist", id)),
         }
     }
+    fn wrong_type(&self, expected: &'static str, id: &VcsObjectId) -> VcsError {
+        VcsError::WrongObjectType {
+            expected,
+            found: self.kind(),
+            id: get_human_id(id),
+        }
+    }
+    /// Returns the ids of the objects this object refers to together with the
+    /// types those objects are expected to have.
+    pub fn references(&self) -> Vec<(VcsObjectId, &'static str)> {
+        match self {
+            VcsObjects::Commit(commit) => {
+                let mut references = vec![(commit.tree, "tree")];
+                references.extend(commit.parents().map(|parent| (*parent, "commit")));
+                references
+            }
+            VcsObjects::Blob(_) | VcsObjects::Chunk(_) => Vec::new(),
+            VcsObjects::ChunkList(chunk_list) => {
+                chunk_list.chunks.iter().map(|id| (*id, "chunk")).collect()
+            }
+            VcsObjects::Tree(tree) => tree
+                .iter()
+                .map(|node| (node.id, if node.is_blob() { "blob" } else { "tree" }))
+                .collect(),
+        }
+    }
+    /// Returns the object's type together with its references.
+    pub fn links(&self) -> ObjectLinks {
+        ObjectLinks {
+            kind: self.kind(),
+            references: self.references(),
+        }
+    }
+}
+
+/// The type of an object and the objects it refers to, which is all that
+/// walking the object graph needs.
+#[derive(Debug)]
+pub struct ObjectLinks {
+    pub kind: &'static str,
+    pub references: Vec<(VcsObjectId, &'static str)>,
+}
+impl ObjectLinks {
+    /// Returns true if the object can be used where an object of the expected
+    /// type is. A ChunkList stands for a Blob.
+    pub fn is_kind(&self, expected: &str) -> bool {
+        self.kind == expected || (expected == "blob" && self.kind == "chunklist")
+    }
 }
 
 /// A Commit object representing commits.
@@ -58,15 +349,46 @@ pub struct Commit {
     pub branch: String,
     pub time: SystemTime,
     pub message: String,
+    /// Empty for commits made before authors were recorded. It is left out of
+    /// the object when empty, so that those commits keep their ids.
+    #[serde(default, skip_serializing_if = "String::is_empty")]
+    pub author: String,
+    /// Heads of the branches a merge commit merged into `parent`. Left out of
+    /// the object when empty, like `author`.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub merged: Vec<VcsObjectId>,
+}
+impl Commit {
+    /// Iterates over all the commit's parents, `parent` first.
+    pub fn parents(&self) -> impl Iterator<Item = &VcsObjectId> {
+        self.parent.iter().chain(self.merged.iter())
+    }
 }
 
 /// A Blob object representing files.
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct Blob {
-    pub file_name: String,
+    #[serde(with = "super::raw_path")]
+    pub file_name: PathBuf,
+    pub data: Bytes,
+}
+
+/// A piece of the data of a large file.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Chunk {
     pub data: Bytes,
 }
 
+/// A large file stored as content-defined Chunks, so that the parts of the
+/// file that don't change between versions are stored once. It is used in
+/// trees in place of a Blob.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ChunkList {
+    #[serde(with = "super::raw_path")]
+    pub file_name: PathBuf,
+    pub chunks: Vec<VcsObjectId>,
+}
+
 /// A Tree object representing directories.
 #[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 pub struct Tree(Vec<TreeNode>);
@@ -98,6 +420,10 @@ impl Tree {
     pub fn iter(&self) -> std::slice::Iter<'_, TreeNode> {
         self.0.iter()
     }
+    /// Gets a parallel iterator over the tree's direct children.
+    pub fn par_iter(&self) -> rayon::slice::Iter<'_, TreeNode> {
+        self.0.par_iter()
+    }
     /// Creates and iterator from the tree's direct children values.
     pub fn into_iter(self) -> std::vec::IntoIter<TreeNode> {
         self.0.into_iter()
@@ -108,7 +434,12 @@ impl Tree {
 pub struct TreeNode {
     object_type: TreeNodeType,
     pub id: VcsObjectId,
+    #[serde(with = "super::raw_path")]
     pub path: PathBuf,
+    /// Mode of the file a Blob node corresponds to. Regular files omit it, so
+    /// that their trees are serialized the same way as before modes existed.
+    #[serde(default, skip_serializing_if = "FileMode::is_regular")]
+    pub mode: FileMode,
 }
 impl TreeNode {
     pub fn new(id: VcsObjectId, path: PathBuf, is_blob: bool) -> Self {
@@ -120,6 +451,14 @@ impl TreeNode {
             },
             id,
             path,
+            mode: FileMode::Regular,
+        }
+    }
+    /// Creates a Blob node for a file with the given mode.
+    pub fn new_file(id: VcsObjectId, path: PathBuf, mode: FileMode) -> Self {
+        Self {
+            mode,
+            ..Self::new(id, path, true)
         }
     }
     /// Returns true if the node corresponds to a Blob object.
@@ -137,39 +476,252 @@ enum TreeNodeType {
     Blob,
 }
 
-#[derive(Debug, Serialize, Deserialize)]
+/// Kind of a file stored in a Blob. The Blob of a symbolic link contains the
+/// link's target.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum FileMode {
+    #[default]
+    Regular,
+    Executable,
+    Symlink,
+    /// A tracked empty directory. Only used in the index, where its id is the
+    /// id of an empty Tree.
+    Directory,
+}
+impl FileMode {
+    pub fn is_regular(&self) -> bool {
+        matches!(self, FileMode::Regular)
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct VcsRepositoryState {
     pub current_commit: Option<VcsObjectId>,
     pub current_branch: String,
 }
 impl VcsSerialize for VcsRepositoryState {}
 
-/// Stores current state of files (their Blob ids) in the working directory.
+/// Repository settings. Settings missing from the config file take their
+/// default values.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct VcsConfig {
+    /// Algorithm used to compute object ids. Can't be changed.
+    pub hash: HashAlgorithm,
+    /// Where objects are stored. Can't be changed.
+    pub object_store: ObjectStoreKind,
+    /// Whether an object's contents are checked against its id on every read.
+    pub verify_objects: bool,
+    /// Whether new empty directories are recorded in commits.
+    pub track_empty_dirs: bool,
+    /// Size in bytes from which files are stored as chunks. 0 disables
+    /// chunking.
+    pub chunk_threshold: u64,
+    /// Name recorded as the author of new commits. When empty, the name of
+    /// the user running vcs is recorded.
+    pub author: String,
+    /// How dates are printed.
+    pub date_format: DateFormat,
+}
+impl VcsSerialize for VcsConfig {}
+impl Default for VcsConfig {
+    fn default() -> Self {
+        Self {
+            hash: HashAlgorithm::default(),
+            object_store: ObjectStoreKind::default(),
+            verify_objects: true,
+            track_empty_dirs: false,
+            chunk_threshold: 8 * 1024 * 1024,
+            author: String::new(),
+            date_format: DateFormat::default(),
+        }
+    }
+}
+impl VcsConfig {
+    /// Returns the value of the setting in json format.
+    pub fn get(&self, key: &str) -> Result<serde_json::Value, VcsError> {
+        let settings = serde_json::to_value(self)?;
+        settings
+            .get(key)
+            .cloned()
+            .ok_or_else(|| VcsError::UnknownSetting(key.to_owned()))
+    }
+    /// Updates the setting. The value is parsed as json if possible and the
+    /// result fits the setting, otherwise it is treated as a string.
+    pub fn set(&mut self, key: &str, value: &str) -> Result<(), VcsError> {
+        if key == "hash" || key == "object_store" {
+            return Err(VcsError::ReadOnlySetting(key.to_owned()));
+        }
+        let settings = serde_json::to_value(&*self)?;
+        if settings.get(key).is_none() {
+            return Err(VcsError::UnknownSetting(key.to_owned()));
+        }
+        let parsed = serde_json::from_str(value).ok();
+        let string = Some(serde_json::Value::String(value.to_owned()));
+        for candidate in [parsed, string].into_iter().flatten() {
+            let mut settings = settings.clone();
+            settings[key] = candidate;
+            if let Ok(config) = serde_json::from_value(settings) {
+                *self = config;
+                return Ok(());
+            }
+        }
+        Err(VcsError::InvalidSetting {
+            key: key.to_owned(),
+            value: value.to_owned(),
+        })
+    }
+}
+
+/// Describes an update of the repository metadata. The journal is written
+/// before the index, heads and STATE are updated and is removed afterwards, so
+/// that an interrupted update can be completed by the next command.
+#[derive(Debug, Serialize, Deserialize)]
+pub struct Journal {
+    pub state: VcsRepositoryState,
+    pub heads: Option<RefStorage>,
+    /// Whether the working tree is updated to match STATE's current commit.
+    pub checkout: bool,
+    /// Commit whose checkout failed and is rolled back. Its files may have
+    /// been written to the working tree.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub rolled_back: Option<VcsObjectId>,
+}
+impl VcsSerialize for Journal {}
+
+/// Stores current state of files (their Blob ids, modes and stat data) in the
+/// working directory.
 #[derive(Debug, Serialize, Deserialize)]
-pub struct Index(BTreeMap<PathBuf, VcsObjectId>);
+#[serde(transparent)]
+pub struct Index {
+    #[serde(with = "super::raw_path::map")]
+    entries: BTreeMap<PathBuf, IndexEntry>,
+    /// Whether the index has been changed since it was loaded.
+    #[serde(skip)]
+    modified: bool,
+}
 impl VcsSerialize for Index {}
 impl Index {
     /// Creates an empty index.
     pub fn new() -> Self {
-        Self(BTreeMap::new())
+        Self {
+            entries: BTreeMap::new(),
+            modified: false,
+        }
     }
-    /// Get current object's id corresponding to the path provided.
-    pub fn get_id(&self, path: &Path) -> &VcsObjectId {
-        self.0.get(path).unwrap()
+    /// Get current entry corresponding to the path provided.
+    pub fn get(&self, path: &Path) -> &IndexEntry {
+        self.entries.get(path).unwrap()
     }
     pub fn contains(&self, path: &Path) -> bool {
-        self.0.contains_key(path)
+        self.entries.contains_key(path)
     }
-    pub fn update(&mut self, path: PathBuf, id: VcsObjectId) {
-        self.0.insert(path, id);
+    pub fn update(&mut self, path: PathBuf, entry: IndexEntry) {
+        self.entries.insert(path, entry);
+        self.modified = true;
+    }
+    /// Records the stat data of an entry whose file has been found unchanged.
+    /// Racy stat data is not recorded.
+    pub fn refresh(&mut self, path: &Path, stat: FileStat) {
+        if let Some(entry) = self.entries.get_mut(path) {
+            let refreshed = entry.with_stat(stat);
+            if *entry != refreshed {
+                *entry = refreshed;
+                self.modified = true;
+            }
+        }
+    }
+    pub fn remove(&mut self, path: &Path) {
+        self.modified |= self.entries.remove(path).is_some();
+    }
+    /// Gets an iterator over the recorded paths and their entries.
+    pub fn iter(&self) -> std::collections::btree_map::Iter<'_, PathBuf, IndexEntry> {
+        self.entries.iter()
     }
     pub fn clear(&mut self) {
-        self.0.clear();
+        self.entries.clear();
+        self.modified = true;
+    }
+    /// Returns true if the index has been changed since it was loaded.
+    pub fn is_modified(&self) -> bool {
+        self.modified
+    }
+}
+
+/// State of a single file recorded in the index.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(from = "IndexEntryRepr")]
+pub struct IndexEntry {
+    pub id: VcsObjectId,
+    pub mode: FileMode,
+    /// File's stat data at the moment it was known to match the Blob. If it
+    /// hasn't changed, the file doesn't need to be hashed to check it.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub stat: Option<FileStat>,
+}
+impl IndexEntry {
+    /// Creates an entry without stat data.
+    pub fn new(id: VcsObjectId, mode: FileMode) -> Self {
+        Self {
+            id,
+            mode,
+            stat: None,
+        }
+    }
+    /// Sets the stat data of the file the entry was created from, unless it is
+    /// racy.
+    pub fn with_stat(mut self, stat: FileStat) -> Self {
+        self.stat = Some(stat).filter(|stat| !stat.is_racy(SystemTime::now()));
+        self
+    }
+}
+
+/// Index entries used to be bare Blob ids, which are still accepted.
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum IndexEntryRepr {
+    Legacy(VcsObjectId),
+    Entry {
+        id: VcsObjectId,
+        mode: FileMode,
+        #[serde(default)]
+        stat: Option<FileStat>,
+    },
+}
+impl From<IndexEntryRepr> for IndexEntry {
+    fn from(repr: IndexEntryRepr) -> Self {
+        match repr {
+            IndexEntryRepr::Legacy(id) => Self::new(id, FileMode::Regular),
+            IndexEntryRepr::Entry { id, mode, stat } => Self { id, mode, stat },
+        }
+    }
+}
+
+/// File metadata used to detect changes without reading the file. Times are
+/// stored as seconds and nanoseconds since the Unix epoch.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct FileStat {
+    pub size: u64,
+    pub mtime: (i64, u32),
+    pub ctime: (i64, u32),
+    pub inode: u64,
+}
+impl FileStat {
+    /// Returns true if the file could still be modified without changing its
+    /// stat data, i.e. its timestamps are not older than the current second.
+    /// Filesystems with coarse timestamps don't tell apart changes made within
+    /// the same tick, so such stat data can't be trusted.
+    pub fn is_racy(&self, now: SystemTime) -> bool {
+        let now = now
+            .duration_since(SystemTime::UNIX_EPOCH)
+            .map_or(0, |now| now.as_secs() as i64);
+        self.mtime.0 >= now || self.ctime.0 >= now
     }
 }
 
 /// Stores vcs objects references
-#[derive(Debug, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct RefStorage(BTreeMap<String, VcsObjectId>);
 impl VcsSerialize for RefStorage {}
 impl RefStorage {
@@ -190,4 +742,76 @@ impl RefStorage {
     pub fn update(&mut self, name: String, id: VcsObjectId) {
         self.0.insert(name, id);
     }
+    /// Gets an iterator over the references and the ids they point to.
+    pub fn iter(&self) -> std::collections::btree_map::Iter<'_, String, VcsObjectId> {
+        self.0.iter()
+    }
+}
+
+#[test]
+fn test_object_id_serialization() {
+    let sha1_id = get_vcs_object_id(&b"hello".to_vec(), HashAlgorithm::Sha1);
+    let legacy_id: [u8; 20] = sha1_id.as_bytes().try_into().unwrap();
+    let content = serde_json::to_vec(&sha1_id).unwrap();
+    assert_eq!(content, serde_json::to_vec(&legacy_id).unwrap());
+    assert_eq!(
+        serde_json::from_slice::<VcsObjectId>(&content).unwrap(),
+        sha1_id
+    );
+
+    let sha256_id = get_vcs_object_id(&b"hello".to_vec(), HashAlgorithm::Sha256);
+    assert_eq!(sha256_id.as_bytes().len(), 32);
+    let content = serde_json::to_vec(&sha256_id).unwrap();
+    assert_eq!(
+        serde_json::from_slice::<VcsObjectId>(&content).unwrap(),
+        sha256_id
+    );
+
+    assert!(serde_json::from_str::<VcsObjectId>("[1, 2, 3]").is_err());
+}
+
+#[test]
+fn test_file_mode_serialization() {
+    let id = get_vcs_object_id(&b"hello".to_vec(), HashAlgorithm::Sha1);
+    let regular = TreeNode::new(id, PathBuf::from("/repo/file"), true);
+    let content = serde_json::to_string(&regular).unwrap();
+    assert!(!content.contains("mode"));
+    assert_eq!(serde_json::from_str::<TreeNode>(&content).unwrap(), regular);
+
+    let script = TreeNode::new_file(id, PathBuf::from("/repo/run.sh"), FileMode::Executable);
+    let content = serde_json::to_string(&script).unwrap();
+    assert!(content.contains(r#""mode":"executable""#));
+    assert_eq!(serde_json::from_str::<TreeNode>(&content).unwrap(), script);
+
+    let legacy = serde_json::to_string(&id).unwrap();
+    let entry = IndexEntry::new(id, FileMode::Regular);
+    assert_eq!(serde_json::from_str::<IndexEntry>(&legacy).unwrap(), entry);
+    let symlink = IndexEntry::new(id, FileMode::Symlink);
+    let content = serde_json::to_string(&symlink).unwrap();
+    assert_eq!(
+        serde_json::from_str::<IndexEntry>(&content).unwrap(),
+        symlink
+    );
+}
+
+#[test]
+fn test_racy_stat() {
+    let now = SystemTime::UNIX_EPOCH + std::time::Duration::new(1000, 500);
+    let stat = FileStat {
+        size: 5,
+        mtime: (999, 999_999_999),
+        ctime: (999, 0),
+        inode: 1,
+    };
+    assert!(!stat.is_racy(now));
+    assert!(FileStat {
+        mtime: (1000, 0),
+        ..stat
+    }
+    .is_racy(now));
+    assert!(FileStat {
+        ctime: (1000, 0),
+        ..stat
+    }
+    .is_racy(now));
 }
@@ -1,17 +1,39 @@
-use crate::errors::VcsResult;
+use crate::errors::{VcsError, VcsResult};
 use crate::vcs_manager::*;
 
-use super::{file_manager, objects::*, traits::VcsSerialize};
+use super::blob_stream::{
+    read_blob_object, verify, write_blob_object, HashingReader, HashingWriter,
+};
+use super::{
+    file_manager, lock::RepositoryLock, objects::*, repository::VcsRepository, traits::VcsSerialize,
+};
 
+use anyhow::Context;
+use fastcdc::v2020::StreamCDC;
+use rayon::prelude::*;
+use std::collections::{BTreeMap, BTreeSet, BinaryHeap};
 use std::fs;
+use std::io::{BufReader, BufWriter, Read, Write};
 use std::path::{Path, PathBuf};
+use std::time::{Duration, SystemTime};
 
 pub const VCS_ROOT: &str = ".vcs";
 const VCS_INDEX: &str = "index.json";
 const VCS_HEADS: &str = "refs/heads.json";
 const VCS_STATE: &str = "STATE.json";
 const VCS_OBJECTS: &str = "objects.json";
+const VCS_DATABASE: &str = "objects.db";
+const VCS_JOURNAL: &str = "JOURNAL.json";
+const VCS_LOCK: &str = "lock";
+const VCS_CONFIG: &str = "config.json";
+const VCS_TMP: &str = "tmp";
 pub const MASTER_BRANCH: &str = "master";
+const CHUNK_MIN_SIZE: u32 = 256 * 1024;
+const CHUNK_AVG_SIZE: u32 = 1024 * 1024;
+const CHUNK_MAX_SIZE: u32 = 4 * 1024 * 1024;
+/// Share of lines a removed and an added file must have in common to be a
+/// rename.
+const RENAME_SIMILARITY: f32 = 0.5;
 
 pub fn get_vcs_root(repo: &Path) -> PathBuf {
     repo.join(VCS_ROOT)
@@ -33,10 +55,36 @@ pub fn get_vcs_index_path(repo: &Path) -> PathBuf {
     get_vcs_entry(repo, VCS_INDEX)
 }
 
+pub fn get_vcs_journal_path(repo: &Path) -> PathBuf {
+    get_vcs_entry(repo, VCS_JOURNAL)
+}
+
+pub fn get_vcs_lock_path(repo: &Path) -> PathBuf {
+    get_vcs_entry(repo, VCS_LOCK)
+}
+
+pub fn get_vcs_config_path(repo: &Path) -> PathBuf {
+    get_vcs_entry(repo, VCS_CONFIG)
+}
+
+pub fn get_vcs_objects_path(repo: &Path) -> PathBuf {
+    get_vcs_entry(repo, VCS_OBJECTS)
+}
+
+pub fn get_vcs_database_path(repo: &Path) -> PathBuf {
+    get_vcs_entry(repo, VCS_DATABASE)
+}
+
+/// Returns the directory of the temporary files objects are written to before
+/// being moved to the objects database.
+pub fn get_vcs_tmp_path(repo: &Path) -> PathBuf {
+    get_vcs_entry(repo, VCS_TMP)
+}
+
 pub fn get_vsc_object_path(repo: &Path, id: &VcsObjectId) -> PathBuf {
     let human_id = get_human_id(id);
     let relative_path = PathBuf::from(&human_id[..2]).join(&human_id[2..]);
-    get_vcs_entry(repo, VCS_OBJECTS).join(relative_path)
+    get_vcs_objects_path(repo).join(relative_path)
 }
 
 pub fn init_state(repo: &Path) -> VcsResult<()> {
@@ -58,135 +106,897 @@ pub fn init_heads(repo: &Path) -> VcsResult<()> {
     Ok(())
 }
 
+pub fn init_config(
+    repo: &Path,
+    hash: HashAlgorithm,
+    object_store: ObjectStoreKind,
+) -> VcsResult<()> {
+    let config = VcsConfig {
+        hash,
+        object_store,
+        ..Default::default()
+    };
+    config.save(&get_vcs_config_path(repo))
+}
+
+/// Loads the repository settings. Repositories without a config file use the
+/// default settings.
+pub fn load_config(repo: &Path) -> VcsResult<VcsConfig> {
+    let config_path = get_vcs_config_path(repo);
+    if config_path.is_file() {
+        VcsConfig::load(&config_path)
+    } else {
+        Ok(VcsConfig::default())
+    }
+}
+
+/// Returns the author of new commits: the one set in the repository settings,
+/// or the name of the user running vcs.
+pub fn get_author(repo: &VcsRepository) -> String {
+    let author = &repo.config().author;
+    if !author.is_empty() {
+        return author.clone();
+    }
+    std::env::var("USER")
+        .or_else(|_| std::env::var("USERNAME"))
+        .unwrap_or_default()
+}
+
+/// Starts an update of the repository metadata by writing its journal. The
+/// working tree and the index are supposed to be updated next, followed by
+/// `finish_update`.
+pub fn begin_update(repo: &Path, journal: &Journal) -> VcsResult<()> {
+    journal.save(&get_vcs_journal_path(repo))
+}
+
+/// Finishes an update of the repository metadata by writing heads and STATE
+/// described by the journal and removing the journal.
+pub fn finish_update(repo: &Path, journal: &Journal) -> VcsResult<()> {
+    if let Some(heads) = &journal.heads {
+        heads.save(&get_vcs_heads_path(repo))?;
+    }
+    journal.state.save(&get_vcs_state_path(repo))?;
+    fs::remove_file(get_vcs_journal_path(repo))?;
+    Ok(())
+}
+
+/// Completes an update of the repository metadata if it was interrupted. The
+/// working tree (if the update was a checkout) and the index are restored from
+/// the journaled STATE's commit, then the update is finished. Untracked files
+/// are left in place.
+pub fn recover_update(repo: &VcsRepository) -> VcsResult<()> {
+    let journal_path = get_vcs_journal_path(repo);
+    if !journal_path.is_file() {
+        return Ok(());
+    }
+    let _lock = RepositoryLock::acquire(repo)?;
+    if !journal_path.is_file() {
+        return Ok(());
+    }
+    restore_update(repo, &Journal::load(&journal_path)?)
+}
+
+/// Restores the working tree (if the update is a checkout) and the index from
+/// the journaled STATE's commit, then finishes the update. Only the files
+/// tracked by the commits the working tree may have been left at (the one in
+/// STATE, the journaled one and the rolled back one) are removed.
+fn restore_update(repo: &VcsRepository, journal: &Journal) -> VcsResult<()> {
+    let mut tracked = Index::new();
+    if journal.checkout {
+        let previous = VcsRepositoryState::load(&get_vcs_state_path(repo))?.current_commit;
+        for commit_id in [previous, journal.rolled_back].iter().flatten() {
+            let tree = load_tree(repo, &load_commit(repo, commit_id)?.tree)?;
+            fill_index(repo, &tree, &mut tracked)?;
+        }
+    }
+    let mut index = Index::new();
+    if let Some(commit_id) = &journal.state.current_commit {
+        let tree = load_tree(repo, &load_commit(repo, commit_id)?.tree)?;
+        if journal.checkout {
+            load_from_tree(repo, &tree, &mut index)?;
+        } else {
+            fill_index(repo, &tree, &mut index)?;
+        }
+    }
+    if journal.checkout {
+        remove_tracked_entries(repo, &tracked, &index)?;
+    }
+    index.save(&get_vcs_index_path(repo))?;
+    finish_update(repo, journal)
+}
+
+/// Updates the working tree and the index to match the tree, then finishes the
+/// update described by the journal. If the working tree can't be updated (e.g.
+/// an object is corrupted), it is rolled back to the current commit and the
+/// error is returned. The rollback is journaled as well, so that the next
+/// command completes it if it fails too.
+pub fn checkout(
+    repo: &VcsRepository,
+    journal: &Journal,
+    tree: &Tree,
+    index: &mut Index,
+) -> VcsResult<()> {
+    assert!(journal.checkout);
+    begin_update(repo, journal)?;
+    let result = load_from_tree(repo, tree, index).and_then(|_| remove_extra_entries(repo, index));
+    if let Err(err) = result {
+        let rollback = Journal {
+            state: VcsRepositoryState::load(&get_vcs_state_path(repo))?,
+            heads: None,
+            checkout: true,
+            rolled_back: journal.state.current_commit,
+        };
+        begin_update(repo, &rollback)?;
+        if let Err(rollback_err) = restore_update(repo, &rollback) {
+            return Err(err.context(format!("Failed to roll back the checkout: {rollback_err}")));
+        }
+        return Err(err);
+    }
+    finish_update(repo, journal)
+}
+
 /// Creates a tree and writes it to the objects database using data contained in
-/// the index.
-pub fn build_tree(repo: &Path, directory: &Path, index: &Index) -> VcsResult<VcsObjectId> {
+/// the index. Subdirectories are processed in parallel.
+pub fn build_tree(repo: &VcsRepository, directory: &Path, index: &Index) -> VcsResult<VcsObjectId> {
+    write_object(
+        repo,
+        &VcsObjects::Tree(build_subtree(repo, directory, index)?),
+    )
+}
+
+/// Creates the tree of the directory, writing the trees of its subdirectories
+/// to the objects database.
+fn build_subtree(repo: &VcsRepository, directory: &Path, index: &Index) -> VcsResult<Tree> {
+    let nodes = file_manager::get_entries(directory, false)
+        .into_par_iter()
+        .map(|entry| {
+            if file_manager::is_dir(&entry) {
+                // Directories without files are only kept if they are tracked.
+                let subtree = build_subtree(repo, &entry, index)?;
+                if subtree.iter().next().is_none() && !index.contains(&entry) {
+                    return Ok(None);
+                }
+                let id = write_object(repo, &VcsObjects::Tree(subtree))?;
+                Ok(Some(TreeNode::new(id, entry, false)))
+            } else {
+                let file = index.get(&entry);
+                Ok(Some(TreeNode::new_file(file.id, entry, file.mode)))
+            }
+        })
+        .collect::<VcsResult<Vec<_>>>()?;
     let mut tree = Tree::new();
-    for entry in file_manager::get_entries(directory, false).into_iter() {
-        let (is_blob, id) = if entry.is_dir() {
-            if file_manager::is_empty_dir(&entry)? {
-                continue;
+    for node in nodes.into_iter().flatten() {
+        tree.add_node(node);
+    }
+    Ok(tree)
+}
+
+/// Computes the id of the serialized object using the repository's hash
+/// algorithm.
+pub fn get_object_id(repo: &VcsRepository, content: &Bytes) -> VcsResult<VcsObjectId> {
+    Ok(get_vcs_object_id(content, repo.config().hash))
+}
+
+/// Serializes the object, writes it to the objects database and returns its
+/// id.
+pub fn write_object(repo: &VcsRepository, object: &VcsObjects) -> VcsResult<VcsObjectId> {
+    let content = object.get_content()?;
+    let id = get_object_id(repo, &content)?;
+    repo.store().put(&id, &content)?;
+    Ok(id)
+}
+
+/// Records changes of the working tree in the index: new and modified files
+/// are added to the objects database in parallel and removed entries are
+/// forgotten.
+pub fn stage_changes(
+    repo: &VcsRepository,
+    changes: &FileChanges,
+    index: &mut Index,
+) -> VcsResult<()> {
+    let entries = changes
+        .par_iter()
+        .map(|(status, path)| match status {
+            FileStatus::Removed => Ok(None),
+            _ if file_manager::is_dir(path) => {
+                let id = write_object(repo, &VcsObjects::Tree(Tree::new()))?;
+                Ok(Some(IndexEntry::new(id, FileMode::Directory)))
             }
-            (false, build_tree(repo, &entry, index)?)
-        } else {
-            let id = index.get_id(&entry);
-            (true, *id)
-        };
-        tree.add_node(TreeNode::new(id, entry, is_blob));
+            _ => Ok(Some(add_blob(repo, path)?)),
+        })
+        .collect::<VcsResult<Vec<_>>>()?;
+    for ((_, path), entry) in changes.iter().zip(entries) {
+        match entry {
+            Some(entry) => index.update(path.to_owned(), entry),
+            None => index.remove(path),
+        }
     }
-    let content = VcsObjects::Tree(tree).get_content()?;
-    let tree_id = get_vcs_object_id(&content);
-    file_manager::write_file(&get_vsc_object_path(repo, &tree_id), &content)?;
-    Ok(tree_id)
+    Ok(())
+}
+
+/// Creates a blob mathching the given file, writes in to the objects database
+/// and returns the index entry describing the file.
+pub fn add_blob(repo: &VcsRepository, file_path: &Path) -> VcsResult<IndexEntry> {
+    // Stat data is taken before the file is read, so that changes made while
+    // it is being read are noticed later.
+    let stat = file_manager::get_file_stat(file_path)?;
+    let mode = file_manager::get_file_mode(file_path)?;
+    let id = write_blob(repo, file_path, mode)?;
+    Ok(IndexEntry::new(id, mode).with_stat(stat))
 }
 
 /// Creates a blob mathching the given file and writes in to the objects
-/// database.
-pub fn add_blob(repo: &Path, file_path: &Path, index: &mut Index) -> VcsResult<VcsObjectId> {
-    let blob = VcsObjects::Blob(make_blob(repo, file_path)?);
-    let content = blob.get_content()?;
-    let id = get_vcs_object_id(&content);
-    index.update(file_path.to_path_buf(), id);
-
-    let blob_path = get_vsc_object_path(repo, &id);
-    file_manager::write_file(&blob_path, &content)?;
-    Ok(id)
+/// database. The file is streamed, so it is never loaded in memory. Files above
+/// the repository's chunk threshold are stored as a ChunkList instead.
+pub fn write_blob(
+    repo: &VcsRepository,
+    file_path: &Path,
+    mode: FileMode,
+) -> VcsResult<VcsObjectId> {
+    if should_chunk(repo, file_path, mode)? {
+        return chunk_blob(repo, file_path, mode, true);
+    }
+    repo.store()
+        .put_with(&mut |writer| Ok(stream_blob(repo, file_path, mode, writer)?.1))
 }
 
-/// Forms a Blob object from file name and its contents.
-fn make_blob(repo: &Path, file_path: &Path) -> VcsResult<Blob> {
-    let data = file_manager::read_file(file_path)?;
-    let file_name = file_manager::get_relative(repo, file_path)
-        .into_os_string()
-        .into_string()
-        .unwrap();
-    Ok(Blob { file_name, data })
+/// Computes the id of the Blob object matching the given file, or the id of
+/// its ChunkList if `chunked` is set. The contents of a symbolic link is its
+/// target.
+pub fn hash_blob(
+    repo: &VcsRepository,
+    file_path: &Path,
+    mode: FileMode,
+    chunked: bool,
+) -> VcsResult<VcsObjectId> {
+    if chunked {
+        return chunk_blob(repo, file_path, mode, false);
+    }
+    Ok(stream_blob(repo, file_path, mode, std::io::sink())?.1)
+}
+
+/// Returns true if the file is large enough to be stored as chunks according
+/// to the repository's settings or too large for the object store to keep as a
+/// single blob. Symbolic links are never chunked.
+pub fn should_chunk(repo: &VcsRepository, file_path: &Path, mode: FileMode) -> VcsResult<bool> {
+    if mode == FileMode::Symlink {
+        return Ok(false);
+    }
+    let size = file_manager::get_file_stat(file_path)?.size;
+    let threshold = repo.config().chunk_threshold;
+    let max_size = repo.store().max_blob_size();
+    Ok((threshold > 0 && size >= threshold) || max_size.is_some_and(|max_size| size > max_size))
+}
+
+/// Splits the file's data into content-defined chunks and returns the id of
+/// the ChunkList referencing them. Chunks that don't change between versions of
+/// the file get the same ids. The objects are written to the objects database
+/// iff `write` is set.
+fn chunk_blob(
+    repo: &VcsRepository,
+    file_path: &Path,
+    mode: FileMode,
+    write: bool,
+) -> VcsResult<VcsObjectId> {
+    let store = |object: &VcsObjects| {
+        if write {
+            write_object(repo, object)
+        } else {
+            get_object_id(repo, &object.get_content()?)
+        }
+    };
+    let data = file_manager::open_data(file_path, mode)?;
+    let mut chunks = Vec::new();
+    for chunk in StreamCDC::new(data, CHUNK_MIN_SIZE, CHUNK_AVG_SIZE, CHUNK_MAX_SIZE) {
+        let chunk = chunk.with_context(|| format!("Failed to read {}", file_path.display()))?;
+        chunks.push(store(&VcsObjects::Chunk(Chunk { data: chunk.data }))?);
+    }
+    store(&VcsObjects::ChunkList(ChunkList {
+        file_name: file_manager::get_relative(repo, file_path),
+        chunks,
+    }))
+}
+
+/// Returns true if the object is a ChunkList. Only the beginning of the object
+/// is read. A missing object is not a ChunkList.
+fn is_chunk_list(repo: &VcsRepository, id: &VcsObjectId) -> VcsResult<bool> {
+    const PREFIX: &[u8] = b"{\"ChunkList\"";
+    let Some(file) = repo.store().open(id)? else {
+        return Ok(false);
+    };
+    let mut prefix = Vec::new();
+    file.take(PREFIX.len() as u64).read_to_end(&mut prefix)?;
+    Ok(prefix == PREFIX)
+}
+
+/// Serializes the Blob object matching the given file to the writer and
+/// returns the writer together with the object's id.
+fn stream_blob<W: Write>(
+    repo: &VcsRepository,
+    file_path: &Path,
+    mode: FileMode,
+    writer: W,
+) -> VcsResult<(W, VcsObjectId)> {
+    let data = file_manager::open_data(file_path, mode)?;
+    let file_name = file_manager::get_relative(repo, file_path);
+    // The hasher is fed with buffered chunks rather than with the small pieces
+    // the serializer produces.
+    let mut writer = BufWriter::new(HashingWriter::new(writer, repo.config().hash));
+    write_blob_object(&mut writer, &file_name, data)
+        .with_context(|| format!("Failed to read {}", file_path.display()))?;
+    let writer = writer.into_inner().map_err(|err| err.into_error())?;
+    Ok(writer.finish())
+}
+
+/// Writes the data of the Blob object to the file with the given mode,
+/// streaming it from the objects database. The data is staged under .vcs/tmp,
+/// where `remove_temp_files` collects it if the checkout is interrupted.
+fn checkout_blob(
+    repo: &VcsRepository,
+    id: &VcsObjectId,
+    path: &Path,
+    mode: FileMode,
+) -> VcsResult<()> {
+    file_manager::write_data_with(path, mode, &get_vcs_tmp_path(repo), |writer| {
+        copy_blob(repo, id, writer)
+    })
+}
+
+/// Reads the data of the Blob object into memory.
+pub fn read_blob(repo: &VcsRepository, id: &VcsObjectId) -> VcsResult<Vec<u8>> {
+    let mut data = Vec::new();
+    copy_blob(repo, id, &mut data)?;
+    Ok(data)
+}
+
+/// Reads the data of the Blob object into memory, unless it is stored as a
+/// ChunkList or is larger than `limit` bytes. Returns None then.
+pub fn read_small_blob(
+    repo: &VcsRepository,
+    id: &VcsObjectId,
+    limit: usize,
+) -> VcsResult<Option<Vec<u8>>> {
+    if is_chunk_list(repo, id)? {
+        return Ok(None);
+    }
+    let mut writer = LimitedWriter {
+        data: Vec::new(),
+        limit,
+        exceeded: false,
+    };
+    match copy_blob(repo, id, &mut writer) {
+        Ok(()) => Ok(Some(writer.data)),
+        Err(_) if writer.exceeded => Ok(None),
+        Err(err) => Err(err),
+    }
+}
+
+/// Collects data in memory, failing once there is more than `limit` bytes.
+struct LimitedWriter {
+    data: Vec<u8>,
+    limit: usize,
+    exceeded: bool,
+}
+
+impl Write for LimitedWriter {
+    fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+        if self.data.len() + buf.len() > self.limit {
+            self.exceeded = true;
+            return Err(std::io::Error::other("data exceeds the size limit"));
+        }
+        self.data.write(buf)
+    }
+
+    fn flush(&mut self) -> std::io::Result<()> {
+        Ok(())
+    }
+}
+
+/// Streams the data of the Blob object from the objects database to the
+/// writer. The data of a ChunkList is written chunk by chunk.
+fn copy_blob(repo: &VcsRepository, id: &VcsObjectId, writer: &mut dyn Write) -> VcsResult<()> {
+    let Some(file) = repo.store().open(id)? else {
+        return Err(VcsError::NoObject(get_human_id(id)).into());
+    };
+    let config = repo.config();
+    let mut reader = BufReader::new(HashingReader::new(file, config.hash));
+    match read_blob_object(&mut reader, &mut *writer)? {
+        None if config.verify_objects => {
+            verify(reader.into_inner(), id, &repo.store().location(id))?
+        }
+        None => (),
+        Some("chunklist") => {
+            for chunk_id in load_object(repo, id)?.chunk_list(id)?.chunks {
+                let chunk = load_object(repo, &chunk_id)?.chunk(&chunk_id)?;
+                writer.write_all(&chunk.data)?;
+            }
+        }
+        Some(found) => {
+            return Err(VcsError::WrongObjectType {
+                expected: "blob",
+                found,
+                id: get_human_id(id),
+            }
+            .into())
+        }
+    }
+    Ok(())
 }
 
 /// Writes the Commit object to the objects database.
-pub fn record_commit(repo: &Path, commit: &Commit) -> VcsResult<VcsObjectId> {
-    let commit_content = VcsObjects::Commit(commit.clone()).get_content()?;
-    let commit_id = get_vcs_object_id(&commit_content);
-    file_manager::write_file(&get_vsc_object_path(repo, &commit_id), &commit_content)?;
-    Ok(commit_id)
+pub fn record_commit(repo: &VcsRepository, commit: &Commit) -> VcsResult<VcsObjectId> {
+    write_object(repo, &VcsObjects::Commit(commit.clone()))
 }
 
 /// Compares two trees by recursively traversing them and returns all the file
-/// changes in the second tree in relation to the first tree.
-pub fn compare_trees(repo: &Path, first: &Tree, second: &Tree) -> VcsResult<FileChanges> {
-    let mut changes = Vec::new();
-    for second_node in second.iter() {
-        let first_node = first.find(&second_node.path, second_node.is_blob());
-        if let Some(first_node) = first_node {
-            if first_node.is_tree() {
-                let first_subtree =
-                    VcsObjects::load(&get_vsc_object_path(repo, &first_node.id))?.tree();
-                let second_subtree =
-                    VcsObjects::load(&get_vsc_object_path(repo, &second_node.id))?.tree();
-                changes.append(&mut compare_trees(repo, &first_subtree, &second_subtree)?);
-            } else if first_node.id != second_node.id {
-                changes.push((FileStatus::Modified, first_node.path.to_owned()))
+/// changes in the second tree in relation to the first tree. Subtrees are
+/// loaded and compared in parallel.
+pub fn compare_trees(repo: &VcsRepository, first: &Tree, second: &Tree) -> VcsResult<FileChanges> {
+    let changes = second
+        .par_iter()
+        .map(|second_node| {
+            let first_node = first.find(&second_node.path, second_node.is_blob());
+            if let Some(first_node) = first_node {
+                if first_node.is_tree() {
+                    let first_subtree = load_tree(repo, &first_node.id)?;
+                    let second_subtree = load_tree(repo, &second_node.id)?;
+                    compare_trees(repo, &first_subtree, &second_subtree)
+                } else if first_node.id != second_node.id || first_node.mode != second_node.mode {
+                    Ok(vec![(FileStatus::Modified, first_node.path.to_owned())])
+                } else {
+                    Ok(Vec::new())
+                }
+            } else if second_node.is_blob() {
+                Ok(vec![(FileStatus::Added, second_node.path.to_owned())])
+            } else {
+                let subtree = load_tree(repo, &second_node.id)?;
+                get_tree_files(repo, &subtree)
+            }
+        })
+        .collect::<VcsResult<Vec<_>>>()?;
+    Ok(changes.into_iter().flatten().collect())
+}
+
+/// Returns the paths of the files of the first tree that are not in the second
+/// tree.
+pub fn get_removed_files(
+    repo: &VcsRepository,
+    first: &Tree,
+    second: &Tree,
+) -> VcsResult<Vec<PathBuf>> {
+    let mut removed = Vec::new();
+    for first_node in first.iter() {
+        match second.find(&first_node.path, first_node.is_blob()) {
+            Some(_) if first_node.is_blob() => {}
+            Some(second_node) => {
+                if second_node.id != first_node.id {
+                    let first_subtree = load_tree(repo, &first_node.id)?;
+                    let second_subtree = load_tree(repo, &second_node.id)?;
+                    removed.append(&mut get_removed_files(
+                        repo,
+                        &first_subtree,
+                        &second_subtree,
+                    )?);
+                }
             }
-        } else if second_node.is_blob() {
-            changes.push((FileStatus::Added, second_node.path.to_owned()));
+            None if first_node.is_blob() => removed.push(first_node.path.to_owned()),
+            None => {
+                let subtree = load_tree(repo, &first_node.id)?;
+                removed.extend(
+                    get_tree_files(repo, &subtree)?
+                        .into_iter()
+                        .map(|(_, path)| path),
+                );
+            }
+        }
+    }
+    Ok(removed)
+}
+
+/// Walks the history back from the starting commits, newest commit first. Each
+/// commit is returned once. Only the first parents are followed iff
+/// `first_parent` is set. Commits are loaded as they are returned, so that
+/// stopping the walk early is cheap.
+pub struct CommitWalk<'a> {
+    repo: &'a VcsRepository,
+    queue: BinaryHeap<(SystemTime, VcsObjectId)>,
+    seen: BTreeSet<VcsObjectId>,
+    first_parent: bool,
+}
+
+impl<'a> CommitWalk<'a> {
+    pub fn new(
+        repo: &'a VcsRepository,
+        start: impl IntoIterator<Item = VcsObjectId>,
+        first_parent: bool,
+    ) -> VcsResult<Self> {
+        let mut walk = Self {
+            repo,
+            queue: BinaryHeap::new(),
+            seen: BTreeSet::new(),
+            first_parent,
+        };
+        for id in start {
+            walk.push(id)?;
+        }
+        Ok(walk)
+    }
+
+    fn push(&mut self, id: VcsObjectId) -> VcsResult<()> {
+        if self.seen.insert(id) {
+            let commit = load_commit(self.repo, &id)?;
+            self.queue.push((commit.time, id));
+        }
+        Ok(())
+    }
+
+    fn next_commit(&mut self) -> VcsResult<Option<(VcsObjectId, Commit)>> {
+        let Some((_, id)) = self.queue.pop() else {
+            return Ok(None);
+        };
+        let commit = load_commit(self.repo, &id)?;
+        let parents: Vec<_> = if self.first_parent {
+            commit.parent.into_iter().collect()
         } else {
-            let subtree = VcsObjects::load(&get_vsc_object_path(repo, &second_node.id))?.tree();
-            changes.append(&mut get_tree_files(repo, &subtree)?);
+            commit.parents().copied().collect()
+        };
+        for parent in parents {
+            self.push(parent)?;
         }
+        Ok(Some((id, commit)))
     }
-    Ok(changes)
 }
 
-pub fn get_tree_files(repo: &Path, tree: &Tree) -> VcsResult<FileChanges> {
+impl Iterator for CommitWalk<'_> {
+    type Item = VcsResult<(VcsObjectId, Commit)>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.next_commit().transpose()
+    }
+}
+
+/// Returns the changes the commit made to its parent's tree. All the files of
+/// a root commit are added.
+pub fn get_commit_changes(repo: &VcsRepository, commit: &Commit) -> VcsResult<FileChanges> {
+    let tree = load_tree(repo, &commit.tree)?;
+    match &commit.parent {
+        Some(parent) => {
+            let parent_tree = load_tree(repo, &load_commit(repo, parent)?.tree)?;
+            compare_trees(repo, &parent_tree, &tree)
+        }
+        None => get_tree_files(repo, &tree),
+    }
+}
+
+/// Returns the paths of the files the commit removed from its parent's tree.
+pub fn get_commit_removals(repo: &VcsRepository, commit: &Commit) -> VcsResult<Vec<PathBuf>> {
+    match &commit.parent {
+        Some(parent) => {
+            let parent_tree = load_tree(repo, &load_commit(repo, parent)?.tree)?;
+            get_removed_files(repo, &parent_tree, &load_tree(repo, &commit.tree)?)
+        }
+        None => Ok(Vec::new()),
+    }
+}
+
+/// Finds the file the commit renamed to `path`, which the commit added: a
+/// removed file with the same contents, or else the removed file most similar
+/// to it, if at least `RENAME_SIMILARITY` of their lines are the same. Only
+/// files of close sizes that can be diffed (see `MAX_DIFF_SIZE`) are compared
+/// line by line.
+pub fn find_rename_source(
+    repo: &VcsRepository,
+    commit: &Commit,
+    removed: &[PathBuf],
+    path: &Path,
+) -> VcsResult<Option<PathBuf>> {
+    let Some(parent) = &commit.parent else {
+        return Ok(None);
+    };
+    let tree = load_tree(repo, &commit.tree)?;
+    let Some(node) = find_blob(repo, &tree, path)? else {
+        return Ok(None);
+    };
+    let (size, digest) = digest_blob(repo, &node.id)?;
+    let parent_tree = load_tree(repo, &load_commit(repo, parent)?.tree)?;
+    let mut candidates = Vec::new();
+    for removed_path in removed {
+        let Some(removed_node) = find_blob(repo, &parent_tree, removed_path)? else {
+            continue;
+        };
+        // Blob ids depend on the file names, so the contents are compared.
+        let (removed_size, removed_digest) = digest_blob(repo, &removed_node.id)?;
+        if removed_size == size && removed_digest == digest {
+            return Ok(Some(removed_path.to_owned()));
+        }
+        let (smaller, larger) = (removed_size.min(size), removed_size.max(size));
+        if smaller as f32 >= larger as f32 * RENAME_SIMILARITY {
+            candidates.push((removed_path, removed_node.id));
+        }
+    }
+    if candidates.is_empty() {
+        return Ok(None);
+    }
+    let Some(data) = read_small_blob(repo, &node.id, line_diff::MAX_DIFF_SIZE)? else {
+        return Ok(None);
+    };
+    let mut source = None;
+    let mut best = RENAME_SIMILARITY;
+    for (removed_path, removed_id) in candidates {
+        let Some(removed_data) = read_small_blob(repo, &removed_id, line_diff::MAX_DIFF_SIZE)?
+        else {
+            continue;
+        };
+        let similarity = line_diff::similarity(&removed_data, &data);
+        if similarity >= best {
+            best = similarity;
+            source = Some(removed_path.to_owned());
+        }
+    }
+    Ok(source)
+}
+
+/// Returns the size of the Blob object's data and a hash of it, streaming the
+/// data rather than loading it in memory.
+fn digest_blob(repo: &VcsRepository, id: &VcsObjectId) -> VcsResult<(u64, VcsObjectId)> {
+    let mut writer = HashingWriter::new(ByteCounter(0), repo.config().hash);
+    copy_blob(repo, id, &mut writer)?;
+    let (ByteCounter(size), digest) = writer.finish();
+    Ok((size, digest))
+}
+
+/// Counts the bytes written to it and discards them.
+struct ByteCounter(u64);
+
+impl Write for ByteCounter {
+    fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+        self.0 += buf.len() as u64;
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> std::io::Result<()> {
+        Ok(())
+    }
+}
+
+/// Finds the Blob node of the file with the given path, looking it up in the
+/// subtrees on the way. Returns None if the tree has no such file.
+pub fn find_blob(repo: &VcsRepository, tree: &Tree, path: &Path) -> VcsResult<Option<TreeNode>> {
+    let mut node = match tree.iter().find(|node| path.starts_with(&node.path)) {
+        Some(node) => node.clone(),
+        None => return Ok(None),
+    };
+    while node.path != path {
+        if node.is_blob() {
+            return Ok(None);
+        }
+        let subtree = load_tree(repo, &node.id)?;
+        node = match subtree.iter().find(|node| path.starts_with(&node.path)) {
+            Some(node) => node.clone(),
+            None => return Ok(None),
+        };
+    }
+    Ok(node.is_blob().then_some(node))
+}
+
+pub fn get_tree_files(repo: &VcsRepository, tree: &Tree) -> VcsResult<FileChanges> {
     let mut files = Vec::new();
     for node in tree.iter() {
         if node.is_blob() {
             files.push((FileStatus::Added, node.path.to_owned()))
         } else {
-            let subtree = VcsObjects::load(&get_vsc_object_path(repo, &node.id))?.tree();
+            let subtree = load_tree(repo, &node.id)?;
             files.append(&mut get_tree_files(repo, &subtree)?);
         }
     }
     Ok(files)
 }
 
+/// Lists the tree's entries with paths relative to the repository root.
+/// Subtrees are expanded into their blobs iff `recursive` is set.
+pub fn get_tree_entries(
+    repo: &VcsRepository,
+    tree: &Tree,
+    recursive: bool,
+) -> VcsResult<Vec<TreeEntryInfo>> {
+    let mut entries = Vec::new();
+    for node in tree.iter() {
+        if recursive && node.is_tree() {
+            let subtree = load_tree(repo, &node.id)?;
+            entries.append(&mut get_tree_entries(repo, &subtree, recursive)?);
+        } else {
+            entries.push(TreeEntryInfo {
+                kind: if node.is_blob() { "blob" } else { "tree" },
+                human_id: get_human_id(&node.id),
+                path: file_manager::get_relative(repo, &node.path),
+            });
+        }
+    }
+    Ok(entries)
+}
+
+/// Loads an object from the objects database. Fails if there is no such
+/// object. Unless disabled in the repository settings, also checks that the
+/// object's contents match its id. Commits and trees are read once and then
+/// taken from the repository's cache.
+pub fn load_object(repo: &VcsRepository, id: &VcsObjectId) -> VcsResult<VcsObjects> {
+    if let Some(object) = repo.cached_object(id) {
+        return Ok(object);
+    }
+    let Some(content) = repo.store().get(id)? else {
+        return Err(VcsError::NoObject(get_human_id(id)).into());
+    };
+    let config = repo.config();
+    if config.verify_objects && get_vcs_object_id(&content, config.hash) != *id {
+        Err(VcsError::CorruptObject(repo.store().location(id)))?;
+    }
+    let object = VcsObjects::read_from(&content)?;
+    repo.cache_object(id, &object);
+    Ok(object)
+}
+
+/// Loads a Commit object. Fails if the object is missing or is not a commit.
+pub fn load_commit(repo: &VcsRepository, id: &VcsObjectId) -> VcsResult<Commit> {
+    Ok(load_object(repo, id)?.commit(id)?)
+}
+
+/// Loads a Tree object. Fails if the object is missing or is not a tree.
+pub fn load_tree(repo: &VcsRepository, id: &VcsObjectId) -> VcsResult<Tree> {
+    Ok(load_object(repo, id)?.tree(id)?)
+}
+
+/// Finds a commit by its public id. Fails if there is no such commit.
+pub fn find_commit(repo: &VcsRepository, human_id: &str) -> VcsResult<(VcsObjectId, Commit)> {
+    let id = get_inner_id(human_id)?;
+    if !repo.store().contains(&id)? {
+        Err(VcsError::NoCommit(human_id.to_owned()))?;
+    }
+    Ok((id, load_commit(repo, &id)?))
+}
+
+/// Loads an object by its public id. Fails if there is no such object in the
+/// objects database.
+pub fn find_object(repo: &VcsRepository, human_id: &str) -> VcsResult<(VcsObjectId, VcsObjects)> {
+    let id = get_inner_id(human_id).map_err(|_| VcsError::NoObject(human_id.to_owned()))?;
+    Ok((id, load_object(repo, &id)?))
+}
+
+/// Finds the commit a revision points to. A revision is either a branch name
+/// or a commit id.
+pub fn resolve_commit(repo: &VcsRepository, rev: &str) -> VcsResult<(VcsObjectId, Commit)> {
+    let heads = RefStorage::load(&get_vcs_heads_path(repo))?;
+    if heads.contains(rev) {
+        let id = *heads.get_id(rev);
+        return Ok((id, load_commit(repo, &id)?));
+    }
+    find_commit(repo, rev)
+}
+
+/// Finds the tree a tree-ish points to. A tree-ish is either a branch name, a
+/// commit id or a tree id.
+pub fn resolve_tree(repo: &VcsRepository, tree_ish: &str) -> VcsResult<Tree> {
+    let heads = RefStorage::load(&get_vcs_heads_path(repo))?;
+    let object = if heads.contains(tree_ish) {
+        load_object(repo, heads.get_id(tree_ish))?
+    } else {
+        find_object(repo, tree_ish)?.1
+    };
+    match object {
+        VcsObjects::Commit(commit) => Ok(load_tree(repo, &commit.tree)?),
+        VcsObjects::Tree(tree) => Ok(tree),
+        _ => Err(VcsError::NotTreeish(tree_ish.to_owned()))?,
+    }
+}
+
 /// Returns the paths of the files whose contents do not match blobs recorded in
-/// the index and the type of change that has happened to them.
-pub fn get_changed_files(repo: &Path, index: &Index) -> VcsResult<FileChanges> {
+/// the index and the type of change that has happened to them. Empty
+/// directories are reported as added if they are tracked. The stat data of the
+/// files found unchanged is refreshed in the index. Files are checked in
+/// parallel.
+pub fn get_changed_files(repo: &VcsRepository, index: &mut Index) -> VcsResult<FileChanges> {
     let files = file_manager::get_all_files(repo, true);
-    files
-        .into_iter()
-        .map(|p| Ok((get_file_status(repo, &p, index)?, p)))
-        .filter_map(|f| match f {
-            Ok((st, _)) if st == FileStatus::Unchanged => None,
-            f => Some(f),
-        })
-        .collect()
+    let statuses = files
+        .par_iter()
+        .map(|path| get_file_status(repo, path, index))
+        .collect::<VcsResult<Vec<_>>>()?;
+    let mut changes = Vec::new();
+    for (path, (status, stat)) in files.into_iter().zip(statuses) {
+        match status {
+            FileStatus::Unchanged => {
+                if let Some(stat) = stat {
+                    index.refresh(&path, stat);
+                }
+            }
+            status => changes.push((status, path)),
+        }
+    }
+    if repo.config().track_empty_dirs {
+        for dir in file_manager::get_empty_dirs(repo)? {
+            if !index.contains(&dir) {
+                changes.push((FileStatus::Added, dir));
+            }
+        }
+    }
+    for (path, entry) in index.iter() {
+        if entry.mode == FileMode::Directory && fs::symlink_metadata(path).is_err() {
+            changes.push((FileStatus::Removed, path.to_owned()));
+        }
+    }
+    changes.sort_by(|a, b| a.1.cmp(&b.1));
+    Ok(changes)
 }
 
 /// Updates working tree so that it matches the tree provided
-pub fn load_from_tree(repo: &Path, tree: &Tree, index: &mut Index) -> VcsResult<()> {
+pub fn load_from_tree(repo: &VcsRepository, tree: &Tree, index: &mut Index) -> VcsResult<()> {
     index.clear();
+    for (path, entry) in write_tree_entries(repo, tree)? {
+        index.update(path, entry);
+    }
+    index.save(&get_vcs_index_path(repo))?;
+    Ok(())
+}
+
+/// Writes all the files of the tree to the working tree in parallel and
+/// returns their index entries. Empty directories are only created and tracked
+/// if the `track_empty_dirs` setting is on.
+fn write_tree_entries(repo: &VcsRepository, tree: &Tree) -> VcsResult<Vec<(PathBuf, IndexEntry)>> {
+    let track_empty_dirs = repo.config().track_empty_dirs;
+    let entries = tree
+        .par_iter()
+        .map(|child| {
+            if child.is_blob() {
+                checkout_blob(repo, &child.id, &child.path, child.mode)?;
+                return Ok(vec![(child.path.to_path_buf(), get_index_entry(child))]);
+            }
+            let subtree = load_tree(repo, &child.id)?;
+            let mut entries = Vec::new();
+            if track_empty_dirs && subtree.iter().next().is_none() {
+                file_manager::write_data(
+                    &child.path,
+                    &[],
+                    FileMode::Directory,
+                    &get_vcs_tmp_path(repo),
+                )?;
+                entries.push((child.path.to_path_buf(), get_directory_entry(child)));
+            }
+            entries.append(&mut write_tree_entries(repo, &subtree)?);
+            Ok(entries)
+        })
+        .collect::<VcsResult<Vec<_>>>()?;
+    Ok(entries.into_iter().flatten().collect())
+}
+
+/// Records all the files of the tree in the index, along with its empty
+/// directories if the `track_empty_dirs` setting is on.
+pub fn fill_index(repo: &VcsRepository, tree: &Tree, index: &mut Index) -> VcsResult<()> {
     for child in tree.iter() {
         if child.is_blob() {
-            let blob = VcsObjects::load(&get_vsc_object_path(repo, &child.id))?.blob();
-            file_manager::write_file(&child.path, &blob.data)?;
-            index.update(child.path.to_path_buf(), child.id);
+            index.update(child.path.to_path_buf(), get_index_entry(child));
         } else {
-            let subtree = VcsObjects::load(&get_vsc_object_path(repo, &child.id))?.tree();
-            load_from_tree(repo, &subtree, index)?;
+            let subtree = load_tree(repo, &child.id)?;
+            if repo.config().track_empty_dirs && subtree.iter().next().is_none() {
+                index.update(child.path.to_path_buf(), get_directory_entry(child));
+            }
+            fill_index(repo, &subtree, index)?;
         }
     }
-    index.save(&get_vcs_index_path(repo))?;
     Ok(())
 }
 
+fn get_index_entry(node: &TreeNode) -> IndexEntry {
+    IndexEntry::new(node.id, node.mode)
+}
+
+fn get_directory_entry(node: &TreeNode) -> IndexEntry {
+    IndexEntry::new(node.id, FileMode::Directory)
+}
+
 /// Removes entries that are not present in the index from the working tree.
 pub fn remove_extra_entries(directory: &Path, index: &Index) -> VcsResult<()> {
     for entry in file_manager::get_entries(directory, false) {
-        if entry.is_dir() {
+        if file_manager::is_dir(&entry) {
             remove_extra_entries(&entry, index)?;
-            if file_manager::is_empty_dir(&entry)? {
+            if file_manager::is_empty_dir(&entry)? && !index.contains(&entry) {
                 fs::remove_dir(&entry)?;
             }
         } else if !index.contains(&entry) {
@@ -196,20 +1006,63 @@ pub fn remove_extra_entries(directory: &Path, index: &Index) -> VcsResult<()> {
     Ok(())
 }
 
+/// Removes the files and empty directories of `tracked` that are not present
+/// in the index from the working tree, along with the directories they leave
+/// empty.
+fn remove_tracked_entries(repo: &Path, tracked: &Index, index: &Index) -> VcsResult<()> {
+    for (path, _) in tracked.iter().rev() {
+        if index.contains(path) {
+            continue;
+        }
+        let Ok(metadata) = fs::symlink_metadata(path) else {
+            continue;
+        };
+        if !metadata.is_dir() {
+            fs::remove_file(path)?;
+        } else if file_manager::is_empty_dir(path)? {
+            fs::remove_dir(path)?;
+        }
+        for parent in path.ancestors().skip(1) {
+            if parent == repo
+                || !parent.starts_with(repo)
+                || index.contains(parent)
+                || !file_manager::is_empty_dir(parent)?
+            {
+                break;
+            }
+            fs::remove_dir(parent)?;
+        }
+    }
+    Ok(())
+}
+
 /// Get the file status in the working tree in relation to the current index.
-fn get_file_status(repo: &Path, file_path: &Path, index: &Index) -> VcsResult<FileStatus> {
-    assert!(!file_path.is_dir());
+/// The file is only hashed if its stat data differs from the one recorded. If
+/// the file is hashed and found unchanged, its current stat data is returned
+/// as well, so that the index can be refreshed.
+fn get_file_status(
+    repo: &VcsRepository,
+    file_path: &Path,
+    index: &Index,
+) -> VcsResult<(FileStatus, Option<FileStat>)> {
+    assert!(!file_manager::is_dir(file_path));
     if !index.contains(file_path) {
-        Ok(FileStatus::Added)
+        return Ok((FileStatus::Added, None));
+    }
+    let entry = index.get(file_path);
+    let stat = file_manager::get_file_stat(file_path)?;
+    if entry.stat == Some(stat) {
+        return Ok((FileStatus::Unchanged, None));
+    }
+    let mode = file_manager::get_file_mode(file_path)?;
+    // The file is hashed the way its recorded version is stored, so that
+    // changing the chunk threshold doesn't make it look modified.
+    let chunked = is_chunk_list(repo, &entry.id)?;
+    let id = hash_blob(repo, file_path, mode, chunked)?;
+    if id == entry.id && mode == entry.mode {
+        Ok((FileStatus::Unchanged, Some(stat)))
     } else {
-        let index_id = index.get_id(file_path);
-        let blob = VcsObjects::Blob(make_blob(repo, file_path)?);
-        let id = get_vcs_object_id(&blob.get_content()?);
-        if id.eq(index_id) {
-            Ok(FileStatus::Unchanged)
-        } else {
-            Ok(FileStatus::Modified)
-        }
+        Ok((FileStatus::Modified, None))
     }
 }
 
@@ -220,6 +1073,7 @@ pub fn merge_trees(source: Tree, mut destination: Tree) -> Tree {
         let dst_node = destination.find_mut(&src_node.path, src_node.is_blob());
         if let Some(dst_node) = dst_node {
             dst_node.id = src_node.id;
+            dst_node.mode = src_node.mode;
         } else {
             destination.add_node(src_node);
         }
@@ -229,6 +1083,7 @@ pub fn merge_trees(source: Tree, mut destination: Tree) -> Tree {
 
 #[test]
 fn test_merge_trees() {
+    let id = |byte| VcsObjectId::from_bytes(&[byte; 20]).unwrap();
     let (file1, file2, dir1, dir2) = (
         PathBuf::from("path/to/file1"),
         PathBuf::from("path/to/file2"),
@@ -236,31 +1091,157 @@ fn test_merge_trees() {
         PathBuf::from("path/to/dir2"),
     );
     let mut source = Tree::new();
-    source.add_node(TreeNode::new([1; 20], file1.to_owned(), true));
-    source.add_node(TreeNode::new([2; 20], file2.to_owned(), true));
-    source.add_node(TreeNode::new([3; 20], dir1.to_owned(), false));
+    source.add_node(TreeNode::new(id(1), file1.to_owned(), true));
+    source.add_node(TreeNode::new(id(2), file2.to_owned(), true));
+    source.add_node(TreeNode::new(id(3), dir1.to_owned(), false));
 
     let mut destination = Tree::new();
-    destination.add_node(TreeNode::new([4; 20], file1.to_owned(), true));
-    destination.add_node(TreeNode::new([5; 20], dir2.to_owned(), false));
+    destination.add_node(TreeNode::new(id(4), file1.to_owned(), true));
+    destination.add_node(TreeNode::new(id(5), dir2.to_owned(), false));
 
     let mut merged = Tree::new();
-    merged.add_node(TreeNode::new([1; 20], file1, true));
-    merged.add_node(TreeNode::new([5; 20], dir2, false));
-    merged.add_node(TreeNode::new([2; 20], file2, true));
-    merged.add_node(TreeNode::new([3; 20], dir1, false));
+    merged.add_node(TreeNode::new(id(1), file1, true));
+    merged.add_node(TreeNode::new(id(5), dir2, false));
+    merged.add_node(TreeNode::new(id(2), file2, true));
+    merged.add_node(TreeNode::new(id(3), dir1, false));
 
     assert_eq!(merge_trees(source, destination), merged);
 }
 
-pub fn get_branch_root(repo: &Path, branch_name: &str, mut head: Commit) -> VcsResult<Commit> {
+pub fn get_branch_root(
+    repo: &VcsRepository,
+    branch_name: &str,
+    mut head: Commit,
+) -> VcsResult<Commit> {
     while head.branch == branch_name {
         let parent = head.parent;
         head = if let Some(parent) = &parent {
-            VcsObjects::load(&get_vsc_object_path(repo, parent))?.commit()
+            load_commit(repo, parent)?
         } else {
             return Ok(head);
         };
     }
     Ok(head)
 }
+
+/// Returns the objects referenced from outside of the objects database, i.e.
+/// branch heads and STATE's current commit, together with the referrers' names.
+pub fn get_roots(repo: &Path) -> VcsResult<Vec<(String, VcsObjectId)>> {
+    let heads = RefStorage::load(&get_vcs_heads_path(repo))?;
+    let mut roots: Vec<_> = heads
+        .iter()
+        .map(|(name, id)| (format!("branch {name}"), *id))
+        .collect();
+    let state = VcsRepositoryState::load(&get_vcs_state_path(repo))?;
+    if let Some(id) = state.current_commit {
+        roots.push(("STATE".to_owned(), id));
+    }
+    Ok(roots)
+}
+
+/// Returns the location of a stored object relative to the repository root.
+/// Stores that don't keep objects on disk report locations that are not in
+/// the repository, those are returned as they are.
+pub fn get_stored_path(repo: &VcsRepository, location: PathBuf) -> PathBuf {
+    match location.strip_prefix(repo) {
+        Ok(path) => path.to_owned(),
+        Err(_) => location,
+    }
+}
+
+/// Reads the type and the references of a stored object. Only commits, trees
+/// and chunk lists are parsed, the data of blobs and chunks is never read.
+/// Fails if the object is missing or can't be parsed.
+pub fn read_links(repo: &VcsRepository, id: &VcsObjectId) -> VcsResult<ObjectLinks> {
+    // The longest tag is the one of `{"ChunkList"`.
+    const PREFIX_LEN: u64 = 12;
+    let Some(mut file) = repo.store().open(id)? else {
+        Err(VcsError::NoObject(get_human_id(id)))?
+    };
+    let mut content = Vec::new();
+    (&mut file).take(PREFIX_LEN).read_to_end(&mut content)?;
+    let tag = content
+        .strip_prefix(b"{\"")
+        .and_then(|rest| rest.split(|byte| *byte == b'"').next());
+    let kind = match tag {
+        Some(b"Blob") => "blob",
+        Some(b"Chunk") => "chunk",
+        _ => {
+            file.read_to_end(&mut content)?;
+            return Ok(VcsObjects::read_from(&content)?.links());
+        }
+    };
+    Ok(ObjectLinks {
+        kind,
+        references: Vec::new(),
+    })
+}
+
+/// Computes the id of a stored object's contents, reading it in pieces.
+pub fn hash_stored_object(repo: &VcsRepository, id: &VcsObjectId) -> VcsResult<VcsObjectId> {
+    let Some(mut file) = repo.store().open(id)? else {
+        Err(VcsError::NoObject(get_human_id(id)))?
+    };
+    let mut writer = HashingWriter::new(std::io::sink(), repo.config().hash);
+    std::io::copy(&mut file, &mut writer)?;
+    Ok(writer.finish().1)
+}
+
+/// Removes the temporary files left behind by object and checkout writes that
+/// didn't complete, unless they were modified less than `grace_period` ago. Returns
+/// the disk space freed.
+pub fn remove_temp_files(repo: &Path, grace_period: Duration) -> VcsResult<u64> {
+    let now = SystemTime::now();
+    let mut freed_bytes = 0;
+    for path in file_manager::get_all_files(&get_vcs_tmp_path(repo), false) {
+        let metadata = fs::metadata(&path)?;
+        let age = now.duration_since(metadata.modified()?).unwrap_or_default();
+        if age >= grace_period {
+            fs::remove_file(&path)?;
+            freed_bytes += metadata.len();
+        }
+    }
+    Ok(freed_bytes)
+}
+
+/// Returns the ids of all the objects that can be reached from the roots
+/// provided by following references between objects.
+pub fn mark_reachable(
+    roots: impl Iterator<Item = VcsObjectId>,
+    objects: &BTreeMap<VcsObjectId, ObjectLinks>,
+) -> BTreeSet<VcsObjectId> {
+    let mut reachable = BTreeSet::new();
+    let mut stack: Vec<_> = roots.collect();
+    while let Some(id) = stack.pop() {
+        if !reachable.insert(id) {
+            continue;
+        }
+        if let Some(links) = objects.get(&id) {
+            stack.extend(links.references.iter().map(|(id, _)| *id));
+        }
+    }
+    reachable
+}
+
+/// Checks that the referenced object exists and has the expected type.
+pub fn check_reference(
+    objects: &BTreeMap<VcsObjectId, ObjectLinks>,
+    id: &VcsObjectId,
+    expected: &'static str,
+    referrer: &str,
+) -> Option<IntegrityIssue> {
+    match objects.get(id) {
+        None => Some(IntegrityIssue::Missing {
+            kind: expected,
+            human_id: get_human_id(id),
+            referrer: referrer.to_owned(),
+        }),
+        Some(object) if !object.is_kind(expected) => Some(IntegrityIssue::WrongType {
+            expected,
+            found: object.kind,
+            human_id: get_human_id(id),
+            referrer: referrer.to_owned(),
+        }),
+        Some(_) => None,
+    }
+}
@@ -2,27 +2,182 @@ use super::file_manager;
 use super::objects::{VcsObjectId, VcsRepositoryState};
 use crate::errors::{VcsError, VcsResult};
 use chrono::{offset::Local, DateTime};
+use regex::Regex;
+use serde::ser::{SerializeSeq, SerializeStruct, Serializer};
+use serde::Serialize;
+use std::fmt;
 use std::path::{Path, PathBuf};
 
+// The public types are serialized for the json output of the commands. Their
+// serialized form is documented in the README and is kept stable: fields may
+// be added, but are never renamed or removed.
+
+#[derive(Serialize)]
 pub struct NewCommitInfo {
+    #[serde(rename = "id")]
     pub human_id: String,
     pub branch: String,
+    #[serde(serialize_with = "serialize_changes")]
     pub changes: FileChanges,
     pub message: String,
 }
 
+#[derive(Serialize)]
 pub struct CommitLog {
+    #[serde(rename = "id")]
     pub human_id: String,
+    #[serde(serialize_with = "serialize_changes")]
     pub changes: FileChanges,
     pub message: String,
     pub time: DateTime<Local>,
+    /// Empty if the commit was made before authors were recorded.
+    pub author: String,
+    /// Public ids of the commit's parents, the first parent first.
+    pub parents: Vec<String>,
+    /// Branches whose heads point to the commit.
+    pub branches: Vec<String>,
+    /// Whether the commit is the current one.
+    pub head: bool,
+    /// Line counts of the changes, if they were asked for.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub stat: Option<Vec<ChangeStat>>,
+}
+
+/// Number of lines a commit added to and removed from a file. Binary files
+/// and files too large to be compared line by line are not counted.
+#[derive(Debug, Serialize)]
+pub struct ChangeStat {
+    #[serde(serialize_with = "serialize_path")]
+    pub path: PathBuf,
+    pub added: Option<usize>,
+    pub removed: Option<usize>,
+}
+
+/// A line of a file together with the commit that added it or last changed
+/// it.
+#[derive(Debug, Serialize)]
+pub struct BlameLine {
+    #[serde(rename = "id")]
+    pub human_id: String,
+    pub author: String,
+    pub time: DateTime<Local>,
+    /// Number of the line in the file, starting from 1.
+    pub line: usize,
+    /// The line without its line ending. Bytes that are not valid UTF-8 are
+    /// replaced.
+    pub content: String,
+}
+
+/// Selects the commits `log` shows. The default filter accepts all of them.
+#[derive(Debug, Clone, Default)]
+pub struct LogFilter {
+    /// Whether the history of all the branches is shown, following all the
+    /// parents of merge commits. Otherwise, only the current commit and its
+    /// first parents are shown.
+    pub all: bool,
+    /// Maximum number of commits shown.
+    pub max_count: Option<usize>,
+    /// Only commits made at or after the time are shown.
+    pub since: Option<DateTime<Local>>,
+    /// Only commits made at or before the time are shown.
+    pub until: Option<DateTime<Local>>,
+    /// Only commits whose message matches are shown.
+    pub grep: Option<Regex>,
+    /// Only commits whose author matches are shown.
+    pub author: Option<Regex>,
+    /// Whether the file of the only path is followed through renames: older
+    /// commits are matched against the names the file had before. Ignored
+    /// unless there is exactly one path.
+    pub follow: bool,
+    /// Only commits changing one of the paths, or a file below one of them,
+    /// are shown. Paths are relative to the repository root. Empty to show
+    /// all commits.
+    pub paths: Vec<PathBuf>,
 }
 
+impl LogFilter {
+    /// Returns true if a commit with the given message, author and time is
+    /// accepted. Paths are checked separately, as they require the commit's
+    /// changes.
+    pub fn accepts(&self, message: &str, author: &str, time: &DateTime<Local>) -> bool {
+        self.since.is_none_or(|since| *time >= since)
+            && self.until.is_none_or(|until| *time <= until)
+            && self.grep.as_ref().is_none_or(|grep| grep.is_match(message))
+            && self.author.as_ref().is_none_or(|re| re.is_match(author))
+    }
+
+    /// Returns true if the changes touch one of the filter's paths.
+    pub fn touches(&self, changes: &[(FileStatus, PathBuf)]) -> bool {
+        self.touches_paths(changes.iter().map(|(_, path)| path.to_owned()))
+    }
+
+    /// Returns true if one of the paths is in one of the filter's paths.
+    pub fn touches_paths(&self, paths: impl IntoIterator<Item = PathBuf>) -> bool {
+        self.paths.is_empty()
+            || paths
+                .into_iter()
+                .any(|path| self.paths.iter().any(|filter| path.starts_with(filter)))
+    }
+}
+
+#[derive(Serialize)]
 pub struct StateInfo {
     pub commit: Option<String>,
     pub branch: String,
 }
 
+/// A branch together with the public id of the commit its head points to.
+#[derive(Serialize)]
+pub struct BranchInfo {
+    pub name: String,
+    pub head: String,
+}
+
+/// Public representation of an object stored in the objects database.
+#[derive(Serialize)]
+#[serde(tag = "type", content = "content", rename_all = "lowercase")]
+pub enum ObjectInfo {
+    Commit {
+        tree: String,
+        parent: Option<String>,
+        branch: String,
+        message: String,
+        time: DateTime<Local>,
+        author: String,
+        /// Heads of the branches merged by a merge commit.
+        merged: Vec<String>,
+    },
+    Blob(Vec<u8>),
+    Tree(Vec<TreeEntryInfo>),
+    Chunk(Vec<u8>),
+    /// Public ids of the chunks of a large file.
+    ChunkList(Vec<String>),
+}
+
+impl ObjectInfo {
+    /// Returns the name of the object's type.
+    pub fn kind(&self) -> &'static str {
+        match self {
+            ObjectInfo::Commit { .. } => "commit",
+            ObjectInfo::Blob(_) => "blob",
+            ObjectInfo::Tree(_) => "tree",
+            ObjectInfo::Chunk(_) => "chunk",
+            ObjectInfo::ChunkList(_) => "chunklist",
+        }
+    }
+}
+
+/// A single tree entry. The path is relative to the repository root.
+#[derive(Serialize)]
+pub struct TreeEntryInfo {
+    #[serde(rename = "type")]
+    pub kind: &'static str,
+    #[serde(rename = "id")]
+    pub human_id: String,
+    #[serde(serialize_with = "serialize_path")]
+    pub path: PathBuf,
+}
+
 impl From<VcsRepositoryState> for StateInfo {
     fn from(state: VcsRepositoryState) -> Self {
         Self {
@@ -41,6 +196,102 @@ impl From<&VcsRepositoryState> for StateInfo {
     }
 }
 
+/// Result of the garbage collection.
+#[derive(Serialize)]
+pub struct GarbageInfo {
+    pub removed_objects: usize,
+    pub freed_bytes: u64,
+    /// Objects that couldn't be parsed and were kept.
+    #[serde(serialize_with = "serialize_paths")]
+    pub corrupt_objects: Vec<PathBuf>,
+}
+
+/// A problem found by the repository integrity check.
+#[derive(Debug, Serialize)]
+#[serde(tag = "issue", rename_all = "snake_case")]
+pub enum IntegrityIssue {
+    /// The object file can't be parsed or its path is not a valid object path.
+    Corrupt {
+        #[serde(serialize_with = "serialize_path")]
+        path: PathBuf,
+    },
+    /// The object's contents don't match the hash in its path.
+    HashMismatch {
+        #[serde(serialize_with = "serialize_path")]
+        path: PathBuf,
+    },
+    /// The referenced object doesn't exist.
+    Missing {
+        #[serde(rename = "type")]
+        kind: &'static str,
+        #[serde(rename = "id")]
+        human_id: String,
+        referrer: String,
+    },
+    /// The referenced object has an unexpected type.
+    WrongType {
+        expected: &'static str,
+        found: &'static str,
+        #[serde(rename = "id")]
+        human_id: String,
+        referrer: String,
+    },
+    /// The object can't be reached and no other object refers to it.
+    Dangling {
+        #[serde(rename = "type")]
+        kind: &'static str,
+        #[serde(rename = "id")]
+        human_id: String,
+    },
+    /// The object can't be reached, but some other unreachable object refers to
+    /// it.
+    Unreachable {
+        #[serde(rename = "type")]
+        kind: &'static str,
+        #[serde(rename = "id")]
+        human_id: String,
+    },
+}
+
+impl IntegrityIssue {
+    /// Returns true if the issue means that the repository is corrupted.
+    pub fn is_error(&self) -> bool {
+        !matches!(
+            self,
+            IntegrityIssue::Dangling { .. } | IntegrityIssue::Unreachable { .. }
+        )
+    }
+}
+
+impl fmt::Display for IntegrityIssue {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            IntegrityIssue::Corrupt { path } => write!(f, "corrupt object: {}", path.display()),
+            IntegrityIssue::HashMismatch { path } => {
+                write!(f, "hash mismatch: {}", path.display())
+            }
+            IntegrityIssue::Missing {
+                kind,
+                human_id,
+                referrer,
+            } => write!(f, "missing {kind} {human_id} (referenced by {referrer})"),
+            IntegrityIssue::WrongType {
+                expected,
+                found,
+                human_id,
+                referrer,
+            } => write!(
+                f,
+                "wrong type: {human_id} is a {found}, expected {expected} (referenced by {referrer})"
+            ),
+            IntegrityIssue::Dangling { kind, human_id } => write!(f, "dangling {kind} {human_id}"),
+            IntegrityIssue::Unreachable { kind, human_id } => {
+                write!(f, "unreachable {kind} {human_id}")
+            }
+        }
+    }
+}
+
 /// Transforms the inner representation of an object id into the public one.
 pub fn get_human_id(id: &VcsObjectId) -> String {
     hex::encode(id)
@@ -48,9 +299,8 @@ pub fn get_human_id(id: &VcsObjectId) -> String {
 
 /// Transforms the public representation of an object id into the inner one.
 pub fn get_inner_id(id: &str) -> VcsResult<VcsObjectId> {
-    let mut bytes = VcsObjectId::default();
-    hex::decode_to_slice(id, &mut bytes).map_err(|_| VcsError::NoCommit(id.to_owned()))?;
-    Ok(bytes)
+    let bytes = hex::decode(id).map_err(|_| VcsError::NoCommit(id.to_owned()))?;
+    Ok(VcsObjectId::from_bytes(&bytes).ok_or_else(|| VcsError::NoCommit(id.to_owned()))?)
 }
 
 #[test]
@@ -58,7 +308,15 @@ fn get_inner_id_works() {
     let human_id = "4c6f72656d20697073756d20677261766964612e";
     let inner_id = get_inner_id(human_id);
     assert!(inner_id.is_ok());
-    assert_eq!(&inner_id.unwrap(), b"Lorem ipsum gravida.");
+    assert_eq!(inner_id.unwrap().as_bytes(), b"Lorem ipsum gravida.");
+}
+
+#[test]
+fn get_sha256_inner_id_works() {
+    let human_id = "4c6f72656d20697073756d20646f6c6f722073697420616d65742c20636f6e2e";
+    let inner_id = get_inner_id(human_id);
+    assert!(inner_id.is_ok());
+    assert_eq!(get_human_id(&inner_id.unwrap()), human_id);
 }
 
 #[test]
@@ -70,15 +328,89 @@ fn incorrect_id() {
 
 /// Represents file statuses in the working tree in relation to the current
 /// index state.  
-#[derive(Debug, Clone, PartialEq, Eq)]
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "lowercase")]
 pub enum FileStatus {
     Modified,
     Added,
+    Removed,
     Unchanged,
 }
 
+impl fmt::Display for FileStatus {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            FileStatus::Modified => "modified",
+            FileStatus::Added => "added",
+            FileStatus::Removed => "removed",
+            FileStatus::Unchanged => "unchanged",
+        })
+    }
+}
+
 pub type FileChanges = Vec<(FileStatus, PathBuf)>;
 
+/// Formats the path for output. Bytes that are not valid UTF-8 are escaped as
+/// `\xNN` and backslashes as `\\`, so that such paths are printed without
+/// loss or ambiguity.
+pub fn escape_path(path: &Path) -> String {
+    let mut escaped = String::new();
+    for chunk in path.as_os_str().as_encoded_bytes().utf8_chunks() {
+        escaped += &chunk.valid().replace('\\', "\\\\");
+        for byte in chunk.invalid() {
+            escaped += &format!("\\x{byte:02x}");
+        }
+    }
+    escaped
+}
+
+#[test]
+fn test_escape_path() {
+    assert_eq!(escape_path(Path::new("dir/caf\u{e9}")), "dir/caf\u{e9}");
+    #[cfg(unix)]
+    {
+        use std::os::unix::ffi::OsStrExt;
+        let path = Path::new(std::ffi::OsStr::from_bytes(b"dir/caf\xe9"));
+        assert_eq!(escape_path(path), "dir/caf\\xe9");
+        assert_eq!(escape_path(Path::new("dir/caf\\xe9")), "dir/caf\\\\xe9");
+    }
+}
+
+/// Serializes the path as a string, escaping the bytes that are not valid
+/// UTF-8 the way the text output does.
+pub fn serialize_path<S: Serializer>(path: &Path, serializer: S) -> Result<S::Ok, S::Error> {
+    serializer.serialize_str(&escape_path(path))
+}
+
+/// Serializes the paths as an array of strings escaped like `serialize_path`.
+pub fn serialize_paths<S: Serializer>(paths: &[PathBuf], serializer: S) -> Result<S::Ok, S::Error> {
+    serializer.collect_seq(paths.iter().map(|path| escape_path(path)))
+}
+
+/// Serializes the changes as an array of `{"status": ..., "path": ...}`
+/// objects.
+pub fn serialize_changes<S: Serializer>(
+    changes: &[(FileStatus, PathBuf)],
+    serializer: S,
+) -> Result<S::Ok, S::Error> {
+    struct Change<'a>(&'a FileStatus, &'a Path);
+
+    impl Serialize for Change<'_> {
+        fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+            let mut change = serializer.serialize_struct("Change", 2)?;
+            change.serialize_field("status", self.0)?;
+            change.serialize_field("path", &escape_path(self.1))?;
+            change.end()
+        }
+    }
+
+    let mut seq = serializer.serialize_seq(Some(changes.len()))?;
+    for (status, path) in changes {
+        seq.serialize_element(&Change(status, path))?;
+    }
+    seq.end()
+}
+
 /// Transforms file paths so that they are relative to the repository root.
 pub fn into_pathspec(repo: &Path, changes: FileChanges) -> FileChanges {
     changes
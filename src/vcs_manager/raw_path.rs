@@ -0,0 +1,163 @@
+use serde::de::{Deserializer, MapAccess, SeqAccess, Visitor};
+use serde::ser::{SerializeSeq, Serializer};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::fmt;
+use std::path::{Path, PathBuf};
+
+/// Serialized form of a path that is not necessarily valid UTF-8. A path is
+/// stored as a string when possible, so that objects written before raw paths
+/// were supported keep their ids, and as an array of its raw bytes otherwise.
+#[derive(Serialize, Deserialize)]
+#[serde(untagged)]
+enum RawPath {
+    Utf8(String),
+    Bytes(Vec<u8>),
+}
+
+pub fn serialize<S: Serializer>(path: &Path, serializer: S) -> Result<S::Ok, S::Error> {
+    to_raw(path).serialize(serializer)
+}
+
+pub fn deserialize<'de, D: Deserializer<'de>>(deserializer: D) -> Result<PathBuf, D::Error> {
+    Ok(from_raw(RawPath::deserialize(deserializer)?))
+}
+
+fn to_raw(path: &Path) -> RawPath {
+    match path.to_str() {
+        Some(path) => RawPath::Utf8(path.to_owned()),
+        None => RawPath::Bytes(to_bytes(path)),
+    }
+}
+
+fn from_raw(path: RawPath) -> PathBuf {
+    match path {
+        RawPath::Utf8(path) => PathBuf::from(path),
+        RawPath::Bytes(bytes) => from_bytes(bytes),
+    }
+}
+
+/// Returns the path's raw bytes. Where paths aren't byte strings, the path is
+/// converted lossily.
+#[cfg(unix)]
+pub fn to_bytes(path: &Path) -> Vec<u8> {
+    use std::os::unix::ffi::OsStrExt;
+    path.as_os_str().as_bytes().to_vec()
+}
+
+#[cfg(not(unix))]
+pub fn to_bytes(path: &Path) -> Vec<u8> {
+    path.to_string_lossy().into_owned().into_bytes()
+}
+
+#[cfg(unix)]
+pub fn from_bytes(bytes: Vec<u8>) -> PathBuf {
+    use std::os::unix::ffi::OsStringExt;
+    PathBuf::from(std::ffi::OsString::from_vec(bytes))
+}
+
+#[cfg(not(unix))]
+pub fn from_bytes(bytes: Vec<u8>) -> PathBuf {
+    PathBuf::from(String::from_utf8_lossy(&bytes).into_owned())
+}
+
+/// Serialization of maps keyed by paths. JSON object keys must be strings, so
+/// the map is stored as an array of `[path, value]` pairs. Maps stored as JSON
+/// objects are still accepted.
+pub mod map {
+    use super::*;
+
+    pub fn serialize<S, V>(map: &BTreeMap<PathBuf, V>, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+        V: Serialize,
+    {
+        let mut seq = serializer.serialize_seq(Some(map.len()))?;
+        for (path, value) in map {
+            seq.serialize_element(&(to_raw(path), value))?;
+        }
+        seq.end()
+    }
+
+    pub fn deserialize<'de, D, V>(deserializer: D) -> Result<BTreeMap<PathBuf, V>, D::Error>
+    where
+        D: Deserializer<'de>,
+        V: Deserialize<'de>,
+    {
+        deserializer.deserialize_any(PathMapVisitor(std::marker::PhantomData))
+    }
+
+    struct PathMapVisitor<V>(std::marker::PhantomData<V>);
+
+    impl<'de, V: Deserialize<'de>> Visitor<'de> for PathMapVisitor<V> {
+        type Value = BTreeMap<PathBuf, V>;
+
+        fn expecting(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+            formatter.write_str("an array of [path, value] pairs or a map")
+        }
+
+        fn visit_seq<A: SeqAccess<'de>>(self, mut seq: A) -> Result<Self::Value, A::Error> {
+            let mut map = BTreeMap::new();
+            while let Some((path, value)) = seq.next_element::<(RawPath, V)>()? {
+                map.insert(from_raw(path), value);
+            }
+            Ok(map)
+        }
+
+        fn visit_map<A: MapAccess<'de>>(self, mut access: A) -> Result<Self::Value, A::Error> {
+            let mut map = BTreeMap::new();
+            while let Some((path, value)) = access.next_entry::<PathBuf, V>()? {
+                map.insert(path, value);
+            }
+            Ok(map)
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[derive(Debug, PartialEq, Serialize, Deserialize)]
+    struct Node {
+        #[serde(with = "super")]
+        path: PathBuf,
+    }
+
+    #[derive(Debug, PartialEq, Serialize, Deserialize)]
+    struct Index(#[serde(with = "map")] BTreeMap<PathBuf, u8>);
+
+    #[test]
+    fn test_utf8_path() {
+        let node = Node {
+            path: PathBuf::from("/repo/file"),
+        };
+        let content = serde_json::to_string(&node).unwrap();
+        assert_eq!(content, r#"{"path":"/repo/file"}"#);
+        assert_eq!(serde_json::from_str::<Node>(&content).unwrap(), node);
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_raw_path() {
+        let node = Node {
+            path: from_bytes(b"/repo/caf\xe9".to_vec()),
+        };
+        let content = serde_json::to_string(&node).unwrap();
+        assert_eq!(content, r#"{"path":[47,114,101,112,111,47,99,97,102,233]}"#);
+        assert_eq!(serde_json::from_str::<Node>(&content).unwrap(), node);
+    }
+
+    #[test]
+    fn test_path_map() {
+        let mut index = Index(BTreeMap::new());
+        index.0.insert(PathBuf::from("/repo/a"), 1);
+        index.0.insert(PathBuf::from("/repo/b"), 2);
+        let content = serde_json::to_string(&index).unwrap();
+        assert_eq!(content, r#"[["/repo/a",1],["/repo/b",2]]"#);
+        assert_eq!(serde_json::from_str::<Index>(&content).unwrap(), index);
+
+        let legacy = r#"{"/repo/a":1,"/repo/b":2}"#;
+        assert_eq!(serde_json::from_str::<Index>(legacy).unwrap(), index);
+    }
+}
@@ -0,0 +1,100 @@
+use super::object_store::{open_store, ObjectStore};
+use super::objects::{VcsConfig, VcsObjectId, VcsObjects};
+use super::objects_manager::load_config;
+use crate::errors::VcsResult;
+
+use lru::LruCache;
+use std::num::NonZeroUsize;
+use std::ops::Deref;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex, RwLock};
+
+/// Number of commits and trees kept in the cache of a repository.
+const CACHE_CAPACITY: NonZeroUsize = match NonZeroUsize::new(4096) {
+    Some(capacity) => capacity,
+    None => unreachable!(),
+};
+
+/// An opened repository: its root directory together with its settings, which
+/// are reloaded whenever a transaction begins, the store its objects are kept
+/// in and a cache of the commits and trees read recently. Objects never
+/// change, so the cache doesn't need to be invalidated.
+///
+/// It dereferences to the root directory, so it can be passed wherever a
+/// repository path is expected.
+#[derive(Debug)]
+pub struct VcsRepository {
+    root: PathBuf,
+    config: RwLock<Arc<VcsConfig>>,
+    store: Box<dyn ObjectStore>,
+    cache: Mutex<LruCache<VcsObjectId, VcsObjects>>,
+}
+
+impl VcsRepository {
+    /// Opens the repository with the given root directory, loads its settings
+    /// and opens the object store they select.
+    pub fn open(root: PathBuf) -> VcsResult<Self> {
+        let config = load_config(&root)?;
+        let store = open_store(&root, config.object_store)?;
+        Ok(Self::with_store(root, config, store))
+    }
+
+    /// Opens the repository with its objects kept in the given store instead
+    /// of the one its settings select.
+    pub fn with_store(root: PathBuf, config: VcsConfig, store: Box<dyn ObjectStore>) -> Self {
+        Self {
+            root,
+            config: RwLock::new(Arc::new(config)),
+            store,
+            cache: Mutex::new(LruCache::new(CACHE_CAPACITY)),
+        }
+    }
+
+    pub fn config(&self) -> Arc<VcsConfig> {
+        self.config.read().unwrap().clone()
+    }
+
+    /// Loads the settings again, as they may have been changed by another
+    /// process since the repository was opened.
+    pub fn reload_config(&self) -> VcsResult<()> {
+        let config = load_config(&self.root)?;
+        *self.config.write().unwrap() = Arc::new(config);
+        Ok(())
+    }
+
+    pub fn store(&self) -> &dyn ObjectStore {
+        self.store.as_ref()
+    }
+
+    /// Replaces the settings. They are supposed to be saved by the caller.
+    pub fn set_config(&mut self, config: VcsConfig) {
+        *self.config.get_mut().unwrap() = Arc::new(config);
+    }
+
+    /// Returns a copy of the object if it is cached.
+    pub fn cached_object(&self, id: &VcsObjectId) -> Option<VcsObjects> {
+        self.cache.lock().unwrap().get(id).cloned()
+    }
+
+    /// Caches the object if it is a commit or a tree. Blobs and chunks are not
+    /// cached, as they are large and rarely read more than once.
+    pub fn cache_object(&self, id: &VcsObjectId, object: &VcsObjects) {
+        if matches!(object, VcsObjects::Commit(_) | VcsObjects::Tree(_)) {
+            self.cache.lock().unwrap().put(*id, object.clone());
+        }
+    }
+}
+
+impl Deref for VcsRepository {
+    type Target = Path;
+
+    fn deref(&self) -> &Path {
+        &self.root
+    }
+}
+
+impl AsRef<Path> for VcsRepository {
+    fn as_ref(&self) -> &Path {
+        &self.root
+    }
+}
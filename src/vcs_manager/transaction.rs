@@ -0,0 +1,59 @@
+use super::lock::RepositoryLock;
+use super::objects::{Index, Journal, RefStorage, Tree, VcsRepositoryState};
+use super::objects_manager::{
+    begin_update, checkout, finish_update, get_vcs_heads_path, get_vcs_index_path,
+    get_vcs_state_path,
+};
+use super::repository::VcsRepository;
+use super::traits::VcsSerialize;
+use crate::errors::VcsResult;
+
+/// An update of the repository metadata. The repository is locked and its
+/// settings, STATE, heads and index are loaded once when the transaction
+/// begins. The metadata is changed in memory and only written by `commit`,
+/// through the journal. A transaction dropped without being committed leaves
+/// the metadata untouched.
+pub struct Transaction<'a> {
+    repo: &'a VcsRepository,
+    _lock: RepositoryLock,
+    pub state: VcsRepositoryState,
+    pub heads: RefStorage,
+    pub index: Index,
+}
+
+impl<'a> Transaction<'a> {
+    /// Locks the repository and loads its settings and metadata.
+    pub fn begin(repo: &'a VcsRepository) -> VcsResult<Self> {
+        let lock = RepositoryLock::acquire(repo)?;
+        repo.reload_config()?;
+        Ok(Self {
+            repo,
+            _lock: lock,
+            state: VcsRepositoryState::load(&get_vcs_state_path(repo))?,
+            heads: RefStorage::load(&get_vcs_heads_path(repo))?,
+            index: Index::load(&get_vcs_index_path(repo))?,
+        })
+    }
+
+    /// Writes the updated metadata. If a tree is given, the working tree and
+    /// the index are first updated to match it. The objects stored so far are
+    /// flushed beforehand, as the metadata may refer to them.
+    pub fn commit(mut self, tree: Option<&Tree>) -> VcsResult<VcsRepositoryState> {
+        self.repo.store().flush()?;
+        let journal = Journal {
+            state: self.state,
+            heads: Some(self.heads),
+            checkout: tree.is_some(),
+            rolled_back: None,
+        };
+        match tree {
+            Some(tree) => checkout(self.repo, &journal, tree, &mut self.index)?,
+            None => {
+                begin_update(self.repo, &journal)?;
+                self.index.save(&get_vcs_index_path(self.repo))?;
+                finish_update(self.repo, &journal)?;
+            }
+        }
+        Ok(journal.state)
+    }
+}
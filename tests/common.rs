@@ -1,9 +1,11 @@
+#![allow(dead_code)]
+
 use assert_cmd::Command;
 use assert_fs::{prelude::*, TempDir};
 use std::env;
 use std::path::Path;
 
-pub const COMMIT_ID_PATTERN: &str = r"[[a-f][0-9]]{40}";
+pub const COMMIT_ID_PATTERN: &str = r"[0-9a-f]{40}(?:[0-9a-f]{24})?";
 
 pub fn get_cmd() -> Command {
     Command::cargo_bin(env!("CARGO_PKG_NAME")).unwrap()
@@ -57,6 +59,7 @@ pub fn jump_to_branch(repo: &Path, name: &str) {
         .expect("It seems that jump cmd doesn't work");
 }
 
+#[allow(clippy::ptr_arg, clippy::needless_borrow)]
 fn get_commit_id(output: &Vec<u8>) -> String {
     let pattern = regex::Regex::new(COMMIT_ID_PATTERN).unwrap();
     pattern
@@ -74,3 +77,9 @@ pub fn create_branch(repo: &Path, name: &str) {
         .ok()
         .expect("It seems that new_branch cmd doesn't work");
 }
+
+pub fn set_config(repo: &Path, key: &str, value: &str) {
+    let mut cmd = get_repo_cmd(repo);
+    cmd.arg("config").arg(key).arg(value);
+    cmd.assert().success();
+}
@@ -0,0 +1,144 @@
+mod common;
+use common::*;
+
+use assert_fs::prelude::{FileWriteStr, PathChild};
+use predicates::prelude::*;
+
+/// Returns the author and the content of each line of the blame.
+fn get_blame(repo: &std::path::Path, args: &[&str]) -> Vec<(String, String)> {
+    let mut cmd = get_repo_cmd(repo);
+    let output = cmd
+        .args(["--format", "json", "blame"])
+        .args(args)
+        .output()
+        .unwrap();
+    assert!(output.status.success(), "{output:?}");
+    let blame: serde_json::Value = serde_json::from_slice(&output.stdout).unwrap();
+    blame
+        .as_array()
+        .unwrap()
+        .iter()
+        .enumerate()
+        .map(|(number, line)| {
+            assert_eq!(line["line"], number + 1);
+            (
+                line["author"].as_str().unwrap().to_owned(),
+                line["content"].as_str().unwrap().to_owned(),
+            )
+        })
+        .collect()
+}
+
+fn blamed(lines: &[(&str, &str)]) -> Vec<(String, String)> {
+    lines
+        .iter()
+        .map(|(author, content)| (author.to_string(), content.to_string()))
+        .collect()
+}
+
+#[test]
+fn test_simple() {
+    let repo = create_test_repo(&[], &[]);
+    init_repo(repo.path());
+    set_config(repo.path(), "author", "alice");
+    repo.child("other").write_str("other\n").unwrap();
+    make_commit(repo.path(), "add other");
+    set_config(repo.path(), "author", "bob");
+    repo.child("dir/file").write_str("1\n2\n3\n").unwrap();
+    make_commit(repo.path(), "add file");
+    set_config(repo.path(), "author", "carol");
+    repo.child("dir/file").write_str("0\n1\ntwo\n3").unwrap();
+    make_commit(repo.path(), "change file");
+
+    let expected = blamed(&[
+        ("carol", "0"),
+        ("bob", "1"),
+        ("carol", "two"),
+        ("carol", "3"),
+    ]);
+    assert_eq!(get_blame(repo.path(), &["dir/file"]), expected);
+    assert_eq!(get_blame(&repo.path().join("dir"), &["file"]), expected);
+
+    let mut cmd = get_repo_cmd(repo.path());
+    cmd.args(["blame", "dir/file"]);
+    cmd.assert().success().stdout(
+        predicate::str::is_match(r"(?m)^[0-9a-f]{7} \(bob   .* 2\) 1$")
+            .unwrap()
+            .count(1),
+    );
+    repo.close().unwrap();
+}
+
+#[test]
+fn test_rev() {
+    let repo = create_test_repo(&[], &[]);
+    init_repo(repo.path());
+    set_config(repo.path(), "author", "alice");
+    repo.child("file").write_str("1\n").unwrap();
+    let first = make_commit(repo.path(), "add file");
+    set_config(repo.path(), "author", "bob");
+    repo.child("file").write_str("1\n2\n").unwrap();
+    make_commit(repo.path(), "change file");
+
+    assert_eq!(
+        get_blame(repo.path(), &["file", "--rev", &first]),
+        blamed(&[("alice", "1")])
+    );
+    assert_eq!(
+        get_blame(repo.path(), &["file", "--rev", "master"]),
+        blamed(&[("alice", "1"), ("bob", "2")])
+    );
+    repo.close().unwrap();
+}
+
+#[test]
+fn test_merge() {
+    let repo = create_test_repo(&[], &[]);
+    init_repo(repo.path());
+    set_config(repo.path(), "author", "alice");
+    repo.child("file").write_str("1\n2\n").unwrap();
+    make_commit(repo.path(), "add file");
+    create_branch(repo.path(), "feat");
+    set_config(repo.path(), "author", "bob");
+    repo.child("file").write_str("0\n1\n2\n").unwrap();
+    make_commit(repo.path(), "change file");
+    jump_to_branch(repo.path(), "master");
+    set_config(repo.path(), "author", "carol");
+    repo.child("other").write_str("other\n").unwrap();
+    make_commit(repo.path(), "add other");
+    let mut cmd = get_repo_cmd(repo.path());
+    cmd.args(["merge", "--branch", "feat"]);
+    cmd.assert().success();
+
+    // The merge commit kept bob's line from the merged branch.
+    assert_eq!(
+        get_blame(repo.path(), &["file"]),
+        blamed(&[("bob", "0"), ("alice", "1"), ("alice", "2")])
+    );
+    repo.close().unwrap();
+}
+
+#[test]
+fn test_no_file() {
+    let repo = create_test_repo(&[], &[]);
+    let first = init_repo(repo.path());
+    repo.child("file").write_str("1\n").unwrap();
+    make_commit(repo.path(), "add file");
+
+    let mut cmd = get_repo_cmd(repo.path());
+    cmd.args(["blame", "missing"]);
+    cmd.assert()
+        .failure()
+        .code(36)
+        .stderr(predicate::str::contains("No file missing exists"));
+
+    // The file was added after the first commit.
+    let mut cmd = get_repo_cmd(repo.path());
+    cmd.args(["blame", "file", "--rev", &first]);
+    cmd.assert().failure().code(36);
+
+    let mut cmd = get_repo_cmd(repo.path());
+    cmd.args(["blame", "../file"]);
+    cmd.assert().failure().code(33);
+    repo.close().unwrap();
+}
@@ -0,0 +1,47 @@
+mod common;
+use common::*;
+
+use assert_fs::prelude::{FileWriteStr, PathChild};
+use predicates::prelude::*;
+
+#[test]
+fn test_commit_type() {
+    let repo = create_test_repo(&[], &[]);
+    let commit_id = init_repo(repo.path());
+    let mut cmd = get_repo_cmd(repo.path());
+    cmd.arg("cat-object").arg("-t").arg(&commit_id);
+    cmd.assert().success().stdout("commit\n");
+    repo.close().unwrap();
+}
+
+#[test]
+fn test_pretty_commit() {
+    let repo = create_test_repo(&[], &[]);
+    init_repo(repo.path());
+    repo.child("file1").write_str("hello world").unwrap();
+    let commit_id = make_commit(repo.path(), "add file1");
+
+    let mut cmd = get_repo_cmd(repo.path());
+    cmd.arg("cat-object").arg("-p").arg(&commit_id);
+    cmd.assert().success().stdout(
+        predicate::str::is_match(format!(
+            r"^tree {0}\nparent {0}\nbranch master\n",
+            COMMIT_ID_PATTERN
+        ))
+        .unwrap()
+        .and(predicate::str::ends_with("\nadd file1\n")),
+    );
+    repo.close().unwrap();
+}
+
+#[test]
+fn test_no_object() {
+    let repo = create_test_repo(&[], &[]);
+    init_repo(repo.path());
+    let mut cmd = get_repo_cmd(repo.path());
+    cmd.arg("cat-object").arg("-p").arg("random_id");
+    cmd.assert()
+        .failure()
+        .stderr(predicate::str::contains("No object with hash random_id"));
+    repo.close().unwrap();
+}
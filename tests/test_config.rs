@@ -0,0 +1,77 @@
+mod common;
+use common::*;
+
+use assert_fs::prelude::{FileWriteStr, PathChild};
+use predicates::prelude::*;
+
+#[test]
+fn test_get_and_set() {
+    let repo = create_test_repo(&[], &[]);
+    init_repo(repo.path());
+    let mut cmd = get_repo_cmd(repo.path());
+    cmd.arg("config").arg("verify_objects");
+    cmd.assert().success().stdout("true\n");
+
+    let mut cmd = get_repo_cmd(repo.path());
+    cmd.arg("config").arg("verify_objects").arg("false");
+    cmd.assert().success().stdout("");
+
+    let mut cmd = get_repo_cmd(repo.path());
+    cmd.arg("config").arg("verify_objects");
+    cmd.assert().success().stdout("false\n");
+    repo.close().unwrap();
+}
+
+#[test]
+fn test_invalid_settings() {
+    let repo = create_test_repo(&[], &[]);
+    init_repo(repo.path());
+    let mut cmd = get_repo_cmd(repo.path());
+    cmd.arg("config").arg("no_such_setting");
+    cmd.assert()
+        .failure()
+        .stderr(predicate::str::contains("No setting no_such_setting"));
+
+    let mut cmd = get_repo_cmd(repo.path());
+    cmd.arg("config").arg("verify_objects").arg("sometimes");
+    cmd.assert().failure().stderr(predicate::str::contains(
+        "Invalid value sometimes for setting verify_objects",
+    ));
+    repo.close().unwrap();
+}
+
+#[test]
+fn test_disable_verification() {
+    let repo = create_test_repo(&[], &[]);
+    repo.child("file1").write_str("hello world").unwrap();
+    init_repo(repo.path());
+    let mut cmd = get_repo_cmd(repo.path());
+    let output = cmd.arg("hash-object").arg("file1").ok().unwrap();
+    let blob_id = String::from_utf8(output.stdout)
+        .unwrap()
+        .trim_end()
+        .to_owned();
+    std::fs::write(
+        repo.path()
+            .join(".vcs/objects.json")
+            .join(&blob_id[..2])
+            .join(&blob_id[2..]),
+        r#"{"Blob":{"file_name":"file1","data":[104]}}"#,
+    )
+    .unwrap();
+
+    let mut cmd = get_repo_cmd(repo.path());
+    cmd.arg("cat-object").arg("-p").arg(&blob_id);
+    cmd.assert()
+        .failure()
+        .stderr(predicate::str::contains("is corrupted"));
+
+    let mut cmd = get_repo_cmd(repo.path());
+    cmd.arg("config").arg("verify_objects").arg("false");
+    cmd.assert().success();
+
+    let mut cmd = get_repo_cmd(repo.path());
+    cmd.arg("cat-object").arg("-p").arg(&blob_id);
+    cmd.assert().success().stdout("h");
+    repo.close().unwrap();
+}
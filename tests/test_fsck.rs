@@ -0,0 +1,103 @@
+mod common;
+use common::*;
+
+use assert_fs::prelude::{FileWriteStr, PathChild};
+use predicates::prelude::*;
+use std::path::{Path, PathBuf};
+
+fn hash_object(repo: &Path, args: &[&str]) -> String {
+    let mut cmd = get_repo_cmd(repo);
+    let output = cmd.arg("hash-object").args(args).ok().unwrap();
+    String::from_utf8(output.stdout)
+        .unwrap()
+        .trim_end()
+        .to_owned()
+}
+
+fn get_object_path(repo: &Path, id: &str) -> PathBuf {
+    repo.join(".vcs/objects.json").join(&id[..2]).join(&id[2..])
+}
+
+#[test]
+fn test_no_problems() {
+    let repo = create_test_repo(&["file1", "subdir/file2"], &[]);
+    init_repo(repo.path());
+    let mut cmd = get_repo_cmd(repo.path());
+    cmd.arg("fsck");
+    cmd.assert().success().stdout("No problems found\n");
+    repo.close().unwrap();
+}
+
+#[test]
+fn test_dangling_blob() {
+    let repo = create_test_repo(&[], &[]);
+    init_repo(repo.path());
+    repo.child("file1").write_str("hello world").unwrap();
+    let blob_id = hash_object(repo.path(), &["-w", "file1"]);
+
+    let mut cmd = get_repo_cmd(repo.path());
+    cmd.arg("fsck");
+    cmd.assert()
+        .success()
+        .stdout(format!("dangling blob {blob_id}\n"));
+    repo.close().unwrap();
+}
+
+#[test]
+fn test_hash_mismatch() {
+    let repo = create_test_repo(&[], &[]);
+    repo.child("file1").write_str("hello world").unwrap();
+    init_repo(repo.path());
+    let blob_id = hash_object(repo.path(), &["file1"]);
+    std::fs::write(
+        get_object_path(repo.path(), &blob_id),
+        r#"{"Blob":{"file_name":"file1","data":[104]}}"#,
+    )
+    .unwrap();
+
+    let mut cmd = get_repo_cmd(repo.path());
+    cmd.arg("fsck");
+    cmd.assert()
+        .failure()
+        .stderr(predicate::str::contains(format!(
+            "hash mismatch: .vcs/objects.json/{}/{}",
+            &blob_id[..2],
+            &blob_id[2..]
+        )));
+    repo.close().unwrap();
+}
+
+#[test]
+fn test_missing_object() {
+    let repo = create_test_repo(&[], &[]);
+    repo.child("file1").write_str("hello world").unwrap();
+    init_repo(repo.path());
+    let blob_id = hash_object(repo.path(), &["file1"]);
+    std::fs::remove_file(get_object_path(repo.path(), &blob_id)).unwrap();
+
+    let mut cmd = get_repo_cmd(repo.path());
+    cmd.arg("fsck");
+    cmd.assert()
+        .failure()
+        .stderr(predicate::str::contains(format!("missing blob {blob_id}")));
+    repo.close().unwrap();
+}
+
+#[test]
+fn test_corrupt_object() {
+    let repo = create_test_repo(&["file1"], &[]);
+    init_repo(repo.path());
+    let corrupt_id = "ab".repeat(20);
+    std::fs::create_dir_all(repo.path().join(".vcs/objects.json/ab")).unwrap();
+    std::fs::write(get_object_path(repo.path(), &corrupt_id), "{\"Tree\":").unwrap();
+
+    let mut cmd = get_repo_cmd(repo.path());
+    cmd.arg("fsck");
+    cmd.assert()
+        .failure()
+        .stderr(predicate::str::contains(format!(
+            "corrupt object: .vcs/objects.json/ab/{}",
+            &corrupt_id[2..]
+        )));
+    repo.close().unwrap();
+}
@@ -0,0 +1,117 @@
+mod common;
+use common::*;
+
+use assert_fs::prelude::{FileWriteStr, PathChild};
+use predicates::prelude::*;
+
+fn write_dangling_blob(repo: &std::path::Path) -> String {
+    let mut cmd = get_repo_cmd(repo);
+    let output = cmd.arg("hash-object").arg("-w").arg("file1").ok().unwrap();
+    String::from_utf8(output.stdout)
+        .unwrap()
+        .trim_end()
+        .to_owned()
+}
+
+#[test]
+fn test_grace_period() {
+    let repo = create_test_repo(&[], &[]);
+    init_repo(repo.path());
+    repo.child("file1").write_str("hello world").unwrap();
+    let blob_id = write_dangling_blob(repo.path());
+
+    let mut cmd = get_repo_cmd(repo.path());
+    cmd.arg("gc");
+    cmd.assert()
+        .success()
+        .stdout("Removed 0 unreachable object(s), freed 0 bytes\n");
+
+    let mut cmd = get_repo_cmd(repo.path());
+    cmd.arg("cat-object").arg("-t").arg(&blob_id);
+    cmd.assert().success().stdout("blob\n");
+    repo.close().unwrap();
+}
+
+#[test]
+fn test_prune_now() {
+    let repo = create_test_repo(&["file2"], &[]);
+    init_repo(repo.path());
+    repo.child("file1").write_str("hello world").unwrap();
+    let blob_id = write_dangling_blob(repo.path());
+
+    let mut cmd = get_repo_cmd(repo.path());
+    cmd.arg("gc").arg("--prune=now");
+    cmd.assert().success().stdout(
+        predicate::str::is_match(r"^Removed 1 unreachable object\(s\), freed [1-9]\d* bytes\n$")
+            .unwrap(),
+    );
+
+    let mut cmd = get_repo_cmd(repo.path());
+    cmd.arg("cat-object").arg("-t").arg(&blob_id);
+    cmd.assert().failure();
+
+    let mut cmd = get_repo_cmd(repo.path());
+    cmd.arg("fsck");
+    cmd.assert().success().stdout("No problems found\n");
+    repo.close().unwrap();
+}
+
+#[test]
+fn test_invalid_age() {
+    let repo = create_test_repo(&[], &[]);
+    init_repo(repo.path());
+    let mut cmd = get_repo_cmd(repo.path());
+    cmd.arg("gc").arg("--prune=yesterday");
+    cmd.assert()
+        .failure()
+        .stderr(predicate::str::contains("invalid age"));
+    repo.close().unwrap();
+}
+
+#[test]
+fn test_corrupt_object() {
+    let repo = create_test_repo(&["file2"], &[]);
+    init_repo(repo.path());
+    repo.child("file1").write_str("hello world").unwrap();
+    let blob_id = write_dangling_blob(repo.path());
+    let corrupt_id = "ab".repeat(20);
+    repo.child(format!(".vcs/objects.json/ab/{}", &corrupt_id[2..]))
+        .write_str("{\"Commit\":")
+        .unwrap();
+
+    let mut cmd = get_repo_cmd(repo.path());
+    cmd.arg("gc").arg("--prune=now");
+    cmd.assert().success().stdout(
+        predicate::str::starts_with(format!(
+            "skipped corrupt object: .vcs/objects.json/ab/{}\nRemoved 1 unreachable object(s)",
+            &corrupt_id[2..]
+        ))
+        .and(predicate::str::contains("freed")),
+    );
+
+    let mut cmd = get_repo_cmd(repo.path());
+    cmd.arg("cat-object").arg("-t").arg(&blob_id);
+    cmd.assert().failure();
+    repo.close().unwrap();
+}
+
+#[test]
+fn test_temp_files() {
+    let repo = create_test_repo(&["file1"], &[]);
+    init_repo(repo.path());
+    repo.child(".vcs/tmp/.file.1.0.tmp")
+        .write_str("interrupted")
+        .unwrap();
+
+    let mut cmd = get_repo_cmd(repo.path());
+    cmd.arg("fsck");
+    cmd.assert().success().stdout("No problems found\n");
+
+    let mut cmd = get_repo_cmd(repo.path());
+    cmd.arg("gc").arg("--prune=now");
+    cmd.assert()
+        .success()
+        .stdout("Removed 0 unreachable object(s), freed 11 bytes\n");
+    assert!(!repo.path().join(".vcs/tmp/.file.1.0.tmp").exists());
+    repo.close().unwrap();
+}
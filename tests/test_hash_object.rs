@@ -0,0 +1,57 @@
+mod common;
+use common::*;
+
+use assert_fs::prelude::{FileWriteStr, PathChild};
+use predicates::prelude::*;
+
+fn hash_object(repo: &std::path::Path, args: &[&str]) -> String {
+    let mut cmd = get_repo_cmd(repo);
+    let output = cmd.arg("hash-object").args(args).ok().unwrap();
+    String::from_utf8(output.stdout).unwrap().trim_end().to_owned()
+}
+
+#[test]
+fn test_matches_committed_blob() {
+    let repo = create_test_repo(&[], &[]);
+    repo.child("file1").write_str("hello world").unwrap();
+    init_repo(repo.path());
+
+    let blob_id = hash_object(repo.path(), &["file1"]);
+    let mut cmd = get_repo_cmd(repo.path());
+    cmd.arg("ls-tree").arg("master");
+    cmd.assert()
+        .success()
+        .stdout(format!("blob {blob_id}\tfile1\n"));
+    repo.close().unwrap();
+}
+
+#[test]
+fn test_write() {
+    let repo = create_test_repo(&[], &[]);
+    init_repo(repo.path());
+    repo.child("file1").write_str("hello world").unwrap();
+
+    let blob_id = hash_object(repo.path(), &["file1"]);
+    let mut cmd = get_repo_cmd(repo.path());
+    cmd.arg("cat-object").arg("-t").arg(&blob_id);
+    cmd.assert().failure();
+
+    assert_eq!(hash_object(repo.path(), &["-w", "file1"]), blob_id);
+    let mut cmd = get_repo_cmd(repo.path());
+    cmd.arg("cat-object").arg("-p").arg(&blob_id);
+    cmd.assert().success().stdout("hello world");
+    repo.close().unwrap();
+}
+
+#[test]
+fn test_outside_repository() {
+    let repo = create_test_repo(&[], &[]);
+    let other = create_test_repo(&["file1"], &[]);
+    init_repo(repo.path());
+    let mut cmd = get_repo_cmd(repo.path());
+    cmd.arg("hash-object").arg(other.child("file1").path());
+    cmd.assert()
+        .failure()
+        .stderr(predicate::str::contains("outside repository"));
+    repo.close().unwrap();
+}
@@ -55,3 +55,132 @@ fn test_double_init() {
         .failure()
         .stderr(predicate::str::contains("Already a vcs repository"));
 }
+
+#[test]
+fn test_sha256() {
+    let repo = create_test_repo(&["file1"], &[]);
+
+    let mut cmd = get_cmd();
+    cmd.arg("init")
+        .arg("--path")
+        .arg(repo.path())
+        .arg("--hash")
+        .arg("sha256");
+    cmd.assert()
+        .success()
+        .stdout(predicate::str::is_match(r"\[master [0-9a-f]{64}\] Initial commit\n").unwrap());
+
+    let mut cmd = get_repo_cmd(repo.path());
+    cmd.arg("config").arg("hash");
+    cmd.assert().success().stdout("sha256\n");
+
+    let mut cmd = get_repo_cmd(repo.path());
+    cmd.arg("ls-tree").arg("master");
+    cmd.assert()
+        .success()
+        .stdout(predicate::str::is_match(r"^blob [0-9a-f]{64}\tfile1\n$").unwrap());
+
+    let mut cmd = get_repo_cmd(repo.path());
+    cmd.arg("fsck");
+    cmd.assert().success().stdout("No problems found\n");
+
+    let mut cmd = get_repo_cmd(repo.path());
+    cmd.arg("config").arg("hash").arg("sha1");
+    cmd.assert()
+        .failure()
+        .stderr(predicate::str::contains("can't be changed"));
+    repo.close().unwrap();
+}
+
+#[test]
+fn test_unknown_hash() {
+    let repo = create_test_repo(&[], &[]);
+    let mut cmd = get_cmd();
+    cmd.arg("init")
+        .arg("--path")
+        .arg(repo.path())
+        .arg("--hash")
+        .arg("md5");
+    cmd.assert()
+        .failure()
+        .stderr(predicate::str::contains("unknown hash algorithm"));
+    repo.close().unwrap();
+}
+
+#[test]
+fn test_database_store() {
+    let repo = create_test_repo(&["file1"], &[]);
+
+    let mut cmd = get_cmd();
+    cmd.arg("init")
+        .arg("--path")
+        .arg(repo.path())
+        .arg("--object-store")
+        .arg("database");
+    cmd.assert().success();
+    assert!(repo.path().join(".vcs/objects.db").is_file());
+    assert!(!repo.path().join(".vcs/objects.json").exists());
+
+    std::fs::write(repo.path().join("file1"), "changed").unwrap();
+    let commit_id = make_commit(repo.path(), "second");
+    let mut cmd = get_repo_cmd(repo.path());
+    cmd.arg("new_branch").arg("--name").arg("feature");
+    cmd.assert().success();
+    std::fs::write(repo.path().join("file1"), "feature").unwrap();
+    make_commit(repo.path(), "third");
+    jump_to_branch(repo.path(), "master");
+    assert_eq!(
+        std::fs::read_to_string(repo.path().join("file1")).unwrap(),
+        "changed"
+    );
+
+    let mut cmd = get_repo_cmd(repo.path());
+    cmd.arg("log");
+    cmd.assert()
+        .success()
+        .stdout(predicate::str::contains(&commit_id));
+
+    let mut cmd = get_repo_cmd(repo.path());
+    cmd.arg("fsck");
+    cmd.assert().success().stdout("No problems found\n");
+
+    let mut cmd = get_repo_cmd(repo.path());
+    cmd.arg("gc").arg("--prune").arg("now");
+    cmd.assert().success();
+
+    let mut cmd = get_repo_cmd(repo.path());
+    cmd.arg("config").arg("object_store").arg("loose");
+    cmd.assert()
+        .failure()
+        .stderr(predicate::str::contains("can't be changed"));
+    repo.close().unwrap();
+}
+
+#[test]
+fn test_database_store_large_file() {
+    let repo = create_test_repo(&[], &[]);
+    let mut cmd = get_cmd();
+    cmd.arg("init")
+        .arg("--path")
+        .arg(repo.path())
+        .arg("--object-store")
+        .arg("database");
+    cmd.assert().success();
+    set_config(repo.path(), "chunk_threshold", "0");
+    std::fs::write(repo.path().join("large"), vec![7u8; 9 * 1024 * 1024]).unwrap();
+    make_commit(repo.path(), "add large");
+
+    let mut cmd = get_repo_cmd(repo.path());
+    let output = cmd.arg("hash-object").arg("large").ok().unwrap();
+    let id = String::from_utf8(output.stdout).unwrap();
+    let mut cmd = get_repo_cmd(repo.path());
+    cmd.arg("cat-object").arg("-t").arg(id.trim());
+    cmd.assert().success().stdout("chunklist\n");
+
+    let mut cmd = get_repo_cmd(repo.path());
+    cmd.arg("status");
+    cmd.assert()
+        .success()
+        .stdout(predicate::str::contains("No changes to be committed"));
+    repo.close().unwrap();
+}
@@ -0,0 +1,170 @@
+mod common;
+use common::*;
+
+use assert_fs::prelude::{FileTouch, FileWriteStr, PathChild};
+use serde_json::{json, Value};
+use std::path::Path;
+
+fn run_json(repo: &Path, args: &[&str]) -> Value {
+    let mut cmd = get_repo_cmd(repo);
+    let output = cmd.arg("--format").arg("json").args(args).output().unwrap();
+    assert!(output.status.success(), "{output:?}");
+    serde_json::from_slice(&output.stdout).unwrap()
+}
+
+#[test]
+fn test_commit_and_log() {
+    let repo = create_test_repo(&["file"], &[]);
+    let init = run_json(repo.path(), &["init", "--path", "."]);
+    let initial_id = init["commit"]["id"].as_str().unwrap().to_owned();
+    assert_eq!(init["commit"]["branch"], "master");
+    assert_eq!(
+        init["commit"]["changes"],
+        json!([{ "status": "added", "path": "file" }])
+    );
+
+    repo.child("file").write_str("changed").unwrap();
+    repo.child("new").touch().unwrap();
+    let status = run_json(repo.path(), &["status"]);
+    assert_eq!(
+        status,
+        json!({
+            "branch": "master",
+            "changes": [
+                { "status": "modified", "path": "file" },
+                { "status": "added", "path": "new" },
+            ],
+        })
+    );
+
+    let commit = run_json(repo.path(), &["commit", "--message", "second"]);
+    assert_eq!(commit["message"], "second");
+    let log = run_json(repo.path(), &["log"]);
+    let log = log.as_array().unwrap();
+    assert_eq!(log.len(), 2);
+    assert_eq!(log[0]["id"], commit["id"]);
+    assert_eq!(log[0]["changes"].as_array().unwrap().len(), 2);
+    assert_eq!(log[1]["id"], initial_id.as_str());
+    assert_eq!(log[1]["message"], "Initial commit");
+    assert!(log[1]["time"].is_string());
+    repo.close().unwrap();
+}
+
+#[test]
+fn test_branches() {
+    let repo = create_test_repo(&["file"], &[]);
+    let initial_id = init_repo(repo.path());
+
+    let branch = run_json(repo.path(), &["new_branch", "--name", "feature"]);
+    assert_eq!(
+        branch,
+        json!({ "commit": initial_id.as_str(), "branch": "feature" })
+    );
+    repo.child("file").write_str("feature").unwrap();
+    let feature_id = make_commit(repo.path(), "feature");
+
+    let jump = run_json(repo.path(), &["jump", "--branch", "master"]);
+    assert_eq!(
+        jump,
+        json!({ "commit": initial_id.as_str(), "branch": "master" })
+    );
+    let merge = run_json(repo.path(), &["merge", "--branch", "feature"]);
+    assert_eq!(merge["branch"], "master");
+    assert_eq!(
+        merge["changes"],
+        json!([{ "status": "modified", "path": "file" }])
+    );
+
+    let jump = run_json(repo.path(), &["jump", "--commit", &feature_id]);
+    assert_eq!(jump["commit"], feature_id.as_str());
+    repo.close().unwrap();
+}
+
+#[test]
+fn test_config() {
+    let repo = create_test_repo(&[], &[]);
+    init_repo(repo.path());
+    let setting = run_json(repo.path(), &["config", "verify_objects"]);
+    assert_eq!(setting, json!({ "key": "verify_objects", "value": true }));
+
+    let setting = run_json(repo.path(), &["config", "author", "123"]);
+    assert_eq!(setting, json!({ "key": "author", "value": "123" }));
+    repo.close().unwrap();
+}
+
+#[test]
+fn test_error() {
+    let repo = create_test_repo(&[], &[]);
+    init_repo(repo.path());
+
+    let mut cmd = get_repo_cmd(repo.path());
+    let output = cmd
+        .args(["--format", "json", "commit", "--message", "nothing"])
+        .output()
+        .unwrap();
+    assert_eq!(output.status.code(), Some(20));
+    assert!(output.stdout.is_empty());
+    let error: Value = serde_json::from_slice(&output.stderr).unwrap();
+    assert_eq!(
+        error,
+        json!({
+            "error": {
+                "kind": "NoChanges",
+                "code": 20,
+                "message": "No changes to be committed",
+            }
+        })
+    );
+    repo.close().unwrap();
+}
+
+fn run_failing_json(repo: &Path, args: &[&str], code: i32) -> Value {
+    let mut cmd = get_repo_cmd(repo);
+    let output = cmd.arg("--format").arg("json").args(args).output().unwrap();
+    assert_eq!(output.status.code(), Some(code), "{output:?}");
+    let error: Value = serde_json::from_slice(&output.stderr).unwrap();
+    assert_eq!(error["error"]["code"], code);
+    error["error"].clone()
+}
+
+#[test]
+fn test_error_fields() {
+    let repo = create_test_repo(&["file"], &[]);
+    init_repo(repo.path());
+    create_branch(repo.path(), "feature");
+    repo.child("file").write_str("feature").unwrap();
+    make_commit(repo.path(), "feature");
+    jump_to_branch(repo.path(), "master");
+    repo.child("file").write_str("master").unwrap();
+    make_commit(repo.path(), "master");
+
+    let error = run_failing_json(repo.path(), &["merge", "--branch", "feature"], 27);
+    assert_eq!(error["kind"], "MergeConflict");
+    assert_eq!(
+        error["both_changed"],
+        json!([{ "status": "modified", "path": "file" }])
+    );
+
+    let missing = "0".repeat(40);
+    let error = run_failing_json(repo.path(), &["jump", "--commit", &missing], 25);
+    assert_eq!(error["kind"], "NoCommit");
+    assert_eq!(error["id"], missing);
+
+    let error = run_failing_json(repo.path(), &["config", "hash", "sha256"], 41);
+    assert_eq!(error["kind"], "ReadOnlySetting");
+    assert_eq!(error["key"], "hash");
+    repo.close().unwrap();
+}
+
+#[test]
+fn test_exit_codes() {
+    let dir = create_test_repo(&[], &[]);
+    let mut cmd = get_repo_cmd(dir.path());
+    cmd.arg("status");
+    cmd.assert().code(10);
+
+    let mut cmd = get_repo_cmd(dir.path());
+    cmd.arg("status").arg("--unknown");
+    cmd.assert().code(2);
+    dir.close().unwrap();
+}
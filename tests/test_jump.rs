@@ -1,7 +1,9 @@
 mod common;
 use common::*;
 
-use assert_fs::prelude::{FileTouch, PathChild};
+use assert_fs::prelude::{
+    FileTouch, FileWriteBin, FileWriteStr, PathAssert, PathChild, PathCreateDir,
+};
 use predicates::prelude::*;
 
 #[test]
@@ -78,3 +80,330 @@ fn test_uncommitted_changes() {
         "local changes to the following files should be commited or dropped",
     ));
 }
+
+#[test]
+fn test_not_a_commit() {
+    let repo = create_test_repo(&["file1"], &[]);
+    let commit_id = init_repo(repo.path());
+    let mut cmd = get_repo_cmd(repo.path());
+    let output = cmd
+        .arg("cat-object")
+        .arg("-p")
+        .arg(&commit_id)
+        .ok()
+        .unwrap();
+    let tree_id = std::str::from_utf8(&output.stdout).unwrap()[5..45].to_owned();
+
+    let mut cmd = get_repo_cmd(repo.path());
+    cmd.arg("jump").arg("--commit").arg(&tree_id);
+    cmd.assert()
+        .failure()
+        .stderr(predicate::str::contains(format!(
+            "Object {tree_id} is a tree, not a commit"
+        )));
+    repo.close().unwrap();
+}
+
+#[test]
+fn test_missing_commit() {
+    let repo = create_test_repo(&[], &[]);
+    init_repo(repo.path());
+    let mut cmd = get_repo_cmd(repo.path());
+    cmd.arg("jump")
+        .arg("--commit")
+        .arg("0123456789abcdef0123456789abcdef01234567");
+    cmd.assert()
+        .failure()
+        .stderr(predicate::str::contains("No commit"));
+    repo.close().unwrap();
+}
+
+#[test]
+fn test_corrupt_blob() {
+    let repo = create_test_repo(&["file"], &[]);
+    let initial_commit_id = init_repo(repo.path());
+    repo.child("file").write_str("changed").unwrap();
+    repo.child("new_file").write_str("hello").unwrap();
+    make_commit(repo.path(), "add new_file");
+    let mut cmd = get_repo_cmd(repo.path());
+    let output = cmd.arg("hash-object").arg("new_file").ok().unwrap();
+    let blob_id = String::from_utf8(output.stdout)
+        .unwrap()
+        .trim_end()
+        .to_owned();
+    let blob_path = repo
+        .path()
+        .join(".vcs/objects.json")
+        .join(&blob_id[..2])
+        .join(&blob_id[2..]);
+    std::fs::write(
+        &blob_path,
+        r#"{"Blob":{"file_name":"new_file","data":[104]}}"#,
+    )
+    .unwrap();
+
+    let mut cmd = get_repo_cmd(repo.path());
+    cmd.arg("jump").arg("--commit").arg(&initial_commit_id);
+    cmd.assert().success();
+
+    let mut cmd = get_repo_cmd(repo.path());
+    cmd.arg("jump").arg("--branch").arg("master");
+    cmd.assert()
+        .failure()
+        .stderr(predicate::str::contains(format!(
+            "Object {} is corrupted",
+            blob_path.display()
+        )));
+    // The working tree is rolled back to the commit it was at.
+    assert!(!repo.child("new_file").exists());
+    repo.child("file").assert("");
+    assert!(!repo.child(".vcs/JOURNAL.json").exists());
+    let mut cmd = get_repo_cmd(repo.path());
+    cmd.arg("status");
+    cmd.assert().success().stdout(predicate::str::contains("No changes"));
+    repo.close().unwrap();
+}
+
+#[test]
+fn test_sha256_repository() {
+    let repo = create_test_repo(&[], &[]);
+    let mut cmd = get_cmd();
+    let output = cmd
+        .arg("init")
+        .arg("--path")
+        .arg(repo.path())
+        .arg("--hash")
+        .arg("sha256")
+        .ok()
+        .unwrap();
+    let pattern = regex::Regex::new(r"[0-9a-f]{64}").unwrap();
+    let stdout = String::from_utf8(output.stdout).unwrap();
+    let initial_commit_id = pattern.find(&stdout).unwrap().as_str().to_owned();
+
+    repo.child("new_file").write_str("hello").unwrap();
+    assert_eq!(make_commit(repo.path(), "add new_file").len(), 64);
+
+    let mut cmd = get_repo_cmd(repo.path());
+    cmd.arg("jump").arg("--commit").arg(&initial_commit_id);
+    cmd.assert().success();
+    assert!(!repo.child("new_file").exists());
+
+    jump_to_branch(repo.path(), "master");
+    assert_eq!(
+        std::fs::read_to_string(repo.child("new_file").path()).unwrap(),
+        "hello"
+    );
+    repo.close().unwrap();
+}
+
+#[cfg(unix)]
+#[test]
+fn test_file_modes() {
+    use std::os::unix::fs::PermissionsExt;
+    let repo = create_test_repo(&[], &["dir"]);
+    let initial_commit_id = init_repo(repo.path());
+    let script = repo.child("script.sh");
+    script.write_str("echo hello").unwrap();
+    std::fs::set_permissions(script.path(), std::fs::Permissions::from_mode(0o755)).unwrap();
+    std::os::unix::fs::symlink("script.sh", repo.child("link").path()).unwrap();
+    std::os::unix::fs::symlink("dir", repo.child("dir_link").path()).unwrap();
+    repo.child("dir/file").write_str("hello").unwrap();
+    let commit_id = make_commit(repo.path(), "add script and links");
+
+    let mut cmd = get_repo_cmd(repo.path());
+    cmd.arg("jump").arg("--commit").arg(&initial_commit_id);
+    cmd.assert().success();
+    assert!(!script.exists());
+    assert!(std::fs::symlink_metadata(repo.child("link").path()).is_err());
+
+    let mut cmd = get_repo_cmd(repo.path());
+    cmd.arg("jump").arg("--commit").arg(&commit_id);
+    cmd.assert().success();
+    let mode = std::fs::metadata(script.path())
+        .unwrap()
+        .permissions()
+        .mode();
+    assert_eq!(mode & 0o111, 0o111);
+    let link = repo.child("link");
+    assert!(link.is_symlink());
+    assert_eq!(
+        std::fs::read_link(link.path()).unwrap().to_str(),
+        Some("script.sh")
+    );
+    assert!(repo.child("dir_link").is_symlink());
+    repo.child("dir/file").assert("hello");
+
+    let mut cmd = get_repo_cmd(repo.path());
+    cmd.arg("status");
+    cmd.assert()
+        .success()
+        .stdout(predicate::str::contains("No changes to be committed"));
+    repo.close().unwrap();
+}
+
+#[test]
+fn test_empty_dirs() {
+    let repo = create_test_repo(&[], &[]);
+    let initial_commit_id = init_repo(repo.path());
+    set_config(repo.path(), "track_empty_dirs", "true");
+    repo.child("logs").create_dir_all().unwrap();
+    repo.child("tmp/cache").create_dir_all().unwrap();
+    let commit_id = make_commit(repo.path(), "add placeholder directories");
+
+    let mut cmd = get_repo_cmd(repo.path());
+    cmd.arg("jump").arg("--commit").arg(&initial_commit_id);
+    cmd.assert().success();
+    assert!(!repo.child("logs").exists());
+    assert!(!repo.child("tmp").exists());
+
+    let mut cmd = get_repo_cmd(repo.path());
+    cmd.arg("jump").arg("--commit").arg(&commit_id);
+    cmd.assert().success();
+    assert!(repo.child("logs").is_dir());
+    assert!(repo.child("tmp/cache").is_dir());
+
+    let mut cmd = get_repo_cmd(repo.path());
+    cmd.arg("status");
+    cmd.assert()
+        .success()
+        .stdout(predicate::str::contains("No changes to be committed"));
+    repo.close().unwrap();
+}
+
+#[test]
+fn test_status_after_jump() {
+    let repo = create_test_repo(&["a", "b/file", "c"], &[]);
+    let initial_commit_id = init_repo(repo.path());
+    repo.child("d").touch().unwrap();
+    make_commit(repo.path(), "add d");
+
+    let mut cmd = get_repo_cmd(repo.path());
+    cmd.arg("jump").arg("--commit").arg(&initial_commit_id);
+    cmd.assert().success();
+
+    let mut cmd = get_repo_cmd(repo.path());
+    cmd.arg("status");
+    cmd.assert()
+        .success()
+        .stdout(predicate::str::contains("No changes to be committed"));
+    repo.close().unwrap();
+}
+
+#[cfg(unix)]
+#[test]
+fn test_non_utf8_path() {
+    use std::os::unix::ffi::OsStrExt;
+    let name = std::ffi::OsStr::from_bytes(b"caf\xe9");
+    let repo = create_test_repo(&[], &[]);
+    repo.child(name).write_str("hello").unwrap();
+    let initial_commit_id = init_repo(repo.path());
+    repo.child(name).write_str("hello world").unwrap();
+
+    let mut cmd = get_repo_cmd(repo.path());
+    cmd.arg("status");
+    cmd.assert()
+        .success()
+        .stdout(predicate::str::contains(r"modified: caf\xe9"));
+    make_commit(repo.path(), "modify the file");
+
+    let mut cmd = get_repo_cmd(repo.path());
+    cmd.arg("jump").arg("--commit").arg(&initial_commit_id);
+    cmd.assert().success();
+    repo.child(name).assert("hello");
+
+    let mut cmd = get_repo_cmd(repo.path());
+    cmd.arg("status");
+    cmd.assert()
+        .success()
+        .stdout(predicate::str::contains("No changes to be committed"));
+    repo.close().unwrap();
+}
+
+#[test]
+fn test_large_file() {
+    let repo = create_test_repo(&[], &[]);
+    let initial_commit_id = init_repo(repo.path());
+    let data: Vec<u8> = (0..3_000_000u32).map(|i| (i * 7 % 256) as u8).collect();
+    repo.child("asset.bin").write_binary(&data).unwrap();
+    make_commit(repo.path(), "add asset");
+
+    let mut cmd = get_repo_cmd(repo.path());
+    cmd.arg("jump").arg("--commit").arg(&initial_commit_id);
+    cmd.assert().success();
+    assert!(!repo.child("asset.bin").exists());
+
+    let mut cmd = get_repo_cmd(repo.path());
+    cmd.arg("jump").arg("--branch").arg("master");
+    cmd.assert().success();
+    assert_eq!(std::fs::read(repo.child("asset.bin").path()).unwrap(), data);
+    repo.close().unwrap();
+}
+
+fn get_output(repo: &std::path::Path, args: &[&str]) -> String {
+    let mut cmd = get_repo_cmd(repo);
+    let output = cmd.args(args).ok().unwrap();
+    String::from_utf8(output.stdout).unwrap()
+}
+
+#[test]
+fn test_chunked_file() {
+    let repo = create_test_repo(&[], &[]);
+    let initial_commit_id = init_repo(repo.path());
+    set_config(repo.path(), "chunk_threshold", "1000000");
+    let mut state = 1u32;
+    let mut data: Vec<u8> = (0..5_000_000)
+        .map(|_| {
+            state ^= state << 13;
+            state ^= state >> 17;
+            state ^= state << 5;
+            state as u8
+        })
+        .collect();
+    repo.child("asset.bin").write_binary(&data).unwrap();
+    make_commit(repo.path(), "add asset");
+
+    let list_id = get_output(repo.path(), &["hash-object", "asset.bin"]);
+    let list_id = list_id.trim();
+    let kind = get_output(repo.path(), &["cat-object", "-t", list_id]);
+    assert_eq!(kind, "chunklist\n");
+    let old_chunks = get_output(repo.path(), &["cat-object", "-p", list_id]);
+    assert!(old_chunks.lines().count() > 1);
+
+    data[2_500_000] ^= 0xff;
+    repo.child("asset.bin").write_binary(&data).unwrap();
+    let mut cmd = get_repo_cmd(repo.path());
+    cmd.arg("status");
+    cmd.assert()
+        .success()
+        .stdout(predicate::str::contains("modified: asset.bin"));
+    make_commit(repo.path(), "modify asset");
+
+    let list_id = get_output(repo.path(), &["hash-object", "asset.bin"]);
+    let new_chunks = get_output(repo.path(), &["cat-object", "-p", list_id.trim()]);
+    let shared = new_chunks
+        .lines()
+        .filter(|chunk| old_chunks.lines().any(|old| old == *chunk))
+        .count();
+    assert!(shared >= new_chunks.lines().count() - 2);
+
+    // Files recorded as chunks are still unchanged when chunking is disabled.
+    set_config(repo.path(), "chunk_threshold", "0");
+    let mut cmd = get_repo_cmd(repo.path());
+    cmd.arg("status");
+    cmd.assert()
+        .success()
+        .stdout(predicate::str::contains("asset.bin").not());
+
+    let mut cmd = get_repo_cmd(repo.path());
+    cmd.arg("jump").arg("--commit").arg(&initial_commit_id);
+    cmd.assert().success();
+    assert!(!repo.child("asset.bin").exists());
+
+    jump_to_branch(repo.path(), "master");
+    assert_eq!(std::fs::read(repo.child("asset.bin").path()).unwrap(), data);
+
+    let mut cmd = get_repo_cmd(repo.path());
+    cmd.arg("fsck");
+    cmd.assert().success();
+    repo.close().unwrap();
+}
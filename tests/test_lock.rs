@@ -0,0 +1,41 @@
+mod common;
+use common::*;
+
+use assert_fs::prelude::{FileWriteStr, PathAssert, PathChild};
+use predicates::prelude::*;
+
+#[test]
+fn test_locked_repository() {
+    let repo = create_test_repo(&[], &[]);
+    init_repo(repo.path());
+    repo.child(".vcs/lock")
+        .write_str(&std::process::id().to_string())
+        .unwrap();
+    repo.child("file1").write_str("hello world").unwrap();
+
+    let mut cmd = get_repo_cmd(repo.path());
+    cmd.arg("commit").arg("--message").arg("add file1");
+    cmd.assert()
+        .failure()
+        .stderr(predicate::str::contains("Another vcs process (pid"));
+    repo.child(".vcs/lock")
+        .assert(std::process::id().to_string());
+    repo.close().unwrap();
+}
+
+#[cfg(unix)]
+#[test]
+fn test_stale_lock() {
+    let repo = create_test_repo(&[], &[]);
+    init_repo(repo.path());
+    let mut child = std::process::Command::new("true").spawn().unwrap();
+    child.wait().unwrap();
+    repo.child(".vcs/lock")
+        .write_str(&child.id().to_string())
+        .unwrap();
+    repo.child("file1").write_str("hello world").unwrap();
+
+    make_commit(repo.path(), "add file1");
+    repo.child(".vcs/lock").assert(predicate::path::missing());
+    repo.close().unwrap();
+}
@@ -1,7 +1,7 @@
 mod common;
 use common::*;
 
-use assert_fs::prelude::{FileTouch, FileWriteStr, PathChild};
+use assert_fs::prelude::{FileTouch, FileWriteBin, FileWriteStr, PathChild, PathCreateDir};
 use predicates::prelude::*;
 
 #[test]
@@ -47,3 +47,400 @@ fn test_branch_logs_root() {
     );
     repo.close().unwrap();
 }
+
+/// Creates a repository with commits "Initial commit", "add a", "add dir/b",
+/// "change a" by alice and "remove dir/b" by bob. The last commit also adds
+/// "c", since a commit has to contain a new or modified file.
+fn create_history() -> assert_fs::TempDir {
+    let repo = create_test_repo(&[], &[]);
+    init_repo(repo.path());
+    set_config(repo.path(), "author", "alice");
+    repo.child("a").touch().unwrap();
+    make_commit(repo.path(), "add a");
+    repo.child("dir/b").touch().unwrap();
+    make_commit(repo.path(), "add dir/b");
+    repo.child("a").write_str("hello").unwrap();
+    make_commit(repo.path(), "change a");
+    set_config(repo.path(), "author", "bob");
+    std::fs::remove_dir_all(repo.child("dir")).unwrap();
+    repo.child("c").touch().unwrap();
+    make_commit(repo.path(), "remove dir/b");
+    repo
+}
+
+fn get_messages(repo: &std::path::Path, args: &[&str]) -> Vec<String> {
+    let mut cmd = get_repo_cmd(repo);
+    let output = cmd.arg("log").args(args).output().unwrap();
+    assert!(output.status.success(), "{output:?}");
+    String::from_utf8(output.stdout)
+        .unwrap()
+        .lines()
+        .filter_map(|line| line.strip_prefix("Message: "))
+        .map(str::to_owned)
+        .collect()
+}
+
+#[test]
+fn test_filters() {
+    let repo = create_history();
+    let path = repo.path();
+    assert_eq!(
+        get_messages(path, &["-n", "2"]),
+        ["remove dir/b", "change a"]
+    );
+    assert_eq!(
+        get_messages(path, &["--grep", "^add"]),
+        ["add dir/b", "add a"]
+    );
+    assert_eq!(
+        get_messages(path, &["--author", "alice"]),
+        ["change a", "add dir/b", "add a"]
+    );
+    assert_eq!(
+        get_messages(path, &["--author", "alice", "-n", "1", "--", "dir"]),
+        ["add dir/b"]
+    );
+    assert_eq!(get_messages(path, &["--", "a"]), ["change a", "add a"]);
+    assert_eq!(
+        get_messages(path, &["--", "dir/b"]),
+        ["remove dir/b", "add dir/b"]
+    );
+    assert_eq!(get_messages(path, &["--since", "1h"]).len(), 5);
+    // Removed files are not listed among the changes of a commit.
+    let mut cmd = get_repo_cmd(path);
+    let output = cmd
+        .args(["--format", "json", "log", "-n", "1"])
+        .output()
+        .unwrap();
+    let logs: serde_json::Value = serde_json::from_slice(&output.stdout).unwrap();
+    assert_eq!(
+        logs[0]["changes"],
+        serde_json::json!([{ "status": "added", "path": "c" }])
+    );
+    assert!(get_messages(path, &["--until", "1h"]).is_empty());
+    assert!(get_messages(path, &["--since", "2999-01-01"]).is_empty());
+    repo.close().unwrap();
+}
+
+#[test]
+fn test_path_from_subdirectory() {
+    let repo = create_history();
+    repo.child("dir").create_dir_all().unwrap();
+    let mut cmd = get_repo_cmd(&repo.path().join("dir"));
+    cmd.arg("log").arg("--").arg("../a");
+    cmd.assert().success().stdout(
+        predicate::str::contains("Message: change a")
+            .and(predicate::str::contains("Message: add dir/b").not())
+            .and(predicate::str::contains("Author: alice")),
+    );
+
+    let mut cmd = get_repo_cmd(repo.path());
+    cmd.arg("log").arg("--").arg("../outside");
+    cmd.assert()
+        .failure()
+        .stderr(predicate::str::contains("outside repository"));
+    repo.close().unwrap();
+}
+
+#[test]
+fn test_invalid_filters() {
+    let repo = create_history();
+    let mut cmd = get_repo_cmd(repo.path());
+    cmd.arg("log").arg("--grep").arg("(");
+    cmd.assert().failure().code(2);
+
+    let mut cmd = get_repo_cmd(repo.path());
+    cmd.arg("log").arg("--since").arg("yesterday");
+    cmd.assert()
+        .failure()
+        .stderr(predicate::str::contains("invalid date"));
+    repo.close().unwrap();
+}
+
+/// Creates a repository where branch "feat" forks off master after "add a" and
+/// is merged back after "add b" on master.
+fn create_merge_history() -> assert_fs::TempDir {
+    let repo = create_test_repo(&[], &[]);
+    init_repo(repo.path());
+    repo.child("a").touch().unwrap();
+    make_commit(repo.path(), "add a");
+    create_branch(repo.path(), "feat");
+    repo.child("c").touch().unwrap();
+    make_commit(repo.path(), "add c");
+    jump_to_branch(repo.path(), "master");
+    repo.child("b").touch().unwrap();
+    make_commit(repo.path(), "add b");
+    let mut cmd = get_repo_cmd(repo.path());
+    cmd.arg("merge").arg("--branch").arg("feat");
+    cmd.assert().success();
+    repo
+}
+
+fn get_graph(repo: &std::path::Path, args: &[&str]) -> Vec<String> {
+    let mut cmd = get_repo_cmd(repo);
+    let output = cmd.arg("log").arg("--graph").args(args).output().unwrap();
+    assert!(output.status.success(), "{output:?}");
+    let id = regex::Regex::new("[0-9a-f]{7} ").unwrap();
+    String::from_utf8(output.stdout)
+        .unwrap()
+        .lines()
+        .map(|line| id.replace(line, "").into_owned())
+        .collect()
+}
+
+#[test]
+fn test_graph() {
+    let repo = create_merge_history();
+    assert_eq!(
+        get_graph(repo.path(), &["--all"]),
+        [
+            "* (HEAD, master) Merged branch feat",
+            "|\\",
+            "* | add b",
+            "| * (feat) add c",
+            "|/",
+            "* add a",
+            "* Initial commit",
+        ]
+    );
+    // Without --all only the first parents are followed.
+    assert_eq!(
+        get_graph(repo.path(), &[]),
+        [
+            "* (HEAD, master) Merged branch feat",
+            "* add b",
+            "* add a",
+            "* Initial commit",
+        ]
+    );
+    repo.close().unwrap();
+}
+
+#[test]
+fn test_merge_parents() {
+    let repo = create_merge_history();
+    let mut cmd = get_repo_cmd(repo.path());
+    cmd.arg("log").arg("-n").arg("1");
+    cmd.assert().success().stdout(
+        predicate::str::is_match(format!(r"commit {COMMIT_ID_PATTERN} \(HEAD, master\)")).unwrap(),
+    );
+
+    let mut cmd = get_repo_cmd(repo.path());
+    let output = cmd
+        .args(["--format", "json", "log", "--all"])
+        .output()
+        .unwrap();
+    let logs: serde_json::Value = serde_json::from_slice(&output.stdout).unwrap();
+    let logs = logs.as_array().unwrap();
+    assert_eq!(logs.len(), 5);
+    assert_eq!(logs[0]["parents"].as_array().unwrap().len(), 2);
+    assert_eq!(logs[0]["head"], true);
+    assert_eq!(logs[0]["branches"], serde_json::json!(["master"]));
+    repo.close().unwrap();
+}
+
+fn get_log(repo: &std::path::Path, args: &[&str]) -> String {
+    let mut cmd = get_repo_cmd(repo);
+    let output = cmd.arg("log").args(args).output().unwrap();
+    assert!(output.status.success(), "{output:?}");
+    String::from_utf8(output.stdout).unwrap()
+}
+
+#[test]
+fn test_oneline_and_pretty() {
+    let repo = create_history();
+    let oneline = get_log(repo.path(), &["--oneline", "-n", "2"]);
+    let lines: Vec<_> = oneline.lines().collect();
+    assert_eq!(lines.len(), 2);
+    assert!(
+        regex::Regex::new(r"^[0-9a-f]{7} \(HEAD, master\) remove dir/b$")
+            .unwrap()
+            .is_match(lines[0]),
+        "{oneline}"
+    );
+    assert!(lines[1].ends_with(" change a"), "{oneline}");
+
+    let pretty = get_log(
+        repo.path(),
+        &["--pretty=format:%an: %s%d [%D] 100%% %x", "-n", "2"],
+    );
+    assert_eq!(
+        pretty,
+        "bob: remove dir/b (HEAD, master) [HEAD, master] 100% %x\n\
+         alice: change a [] 100% %x\n"
+    );
+
+    let ids = get_log(repo.path(), &["--pretty=format:%H %h", "-n", "1"]);
+    let (id, short_id) = ids.trim_end().split_once(' ').unwrap();
+    assert!(id.starts_with(short_id) && short_id.len() == 7, "{ids}");
+    repo.close().unwrap();
+}
+
+#[test]
+fn test_stat() {
+    let repo = create_test_repo(&[], &[]);
+    init_repo(repo.path());
+    repo.child("a").write_str("1\n2\n3\n").unwrap();
+    repo.child("bin").write_binary(b"\0\x01").unwrap();
+    make_commit(repo.path(), "add files");
+    repo.child("a").write_str("1\nx\n3\n4\n").unwrap();
+    repo.child("b").write_str("b\n").unwrap();
+    make_commit(repo.path(), "change a");
+
+    assert_eq!(
+        get_log(repo.path(), &["--pretty=format:%s", "--stat", "-n", "2"]),
+        "change a\n\
+         \x20a | 3 ++-\n\
+         \x20b | 1 +\n\
+         \x202 files changed, 3 insertions(+), 1 deletion(-)\n\
+         add files\n\
+         \x20a   | 3 +++\n\
+         \x20bin | Bin\n\
+         \x202 files changed, 3 insertions(+)\n"
+    );
+
+    let mut cmd = get_repo_cmd(repo.path());
+    let output = cmd
+        .args(["--format", "json", "log", "--stat", "-n", "2"])
+        .output()
+        .unwrap();
+    let logs: serde_json::Value = serde_json::from_slice(&output.stdout).unwrap();
+    assert_eq!(
+        logs[1]["stat"],
+        serde_json::json!([
+            {"path": "a", "added": 3, "removed": 0},
+            {"path": "bin", "added": null, "removed": null},
+        ])
+    );
+    repo.close().unwrap();
+}
+
+#[test]
+fn test_stat_large_files() {
+    let repo = create_test_repo(&[], &[]);
+    init_repo(repo.path());
+    set_config(repo.path(), "chunk_threshold", "0");
+    repo.child("large")
+        .write_str(&"line\n".repeat(220_000))
+        .unwrap();
+    make_commit(repo.path(), "add large");
+    set_config(repo.path(), "chunk_threshold", "1000");
+    repo.child("chunked")
+        .write_str(&"line\n".repeat(1000))
+        .unwrap();
+    make_commit(repo.path(), "add chunked");
+
+    assert_eq!(
+        get_log(repo.path(), &["--pretty=format:%s", "--stat", "-n", "2"]),
+        "add chunked\n\
+         \x20chunked | Bin\n\
+         \x201 file changed\n\
+         add large\n\
+         \x20large | Bin\n\
+         \x201 file changed\n"
+    );
+    repo.close().unwrap();
+}
+
+#[test]
+fn test_date_format() {
+    let repo = create_history();
+    let date = regex::Regex::new(r"^Date: \d{4}-\d{2}-\d{2}$").unwrap();
+    let is_short = |log: &str| log.lines().any(|line| date.is_match(line));
+    assert!(!is_short(&get_log(repo.path(), &["-n", "1"])));
+    assert!(is_short(&get_log(
+        repo.path(),
+        &["-n", "1", "--date=short"]
+    )));
+    assert!(is_short(&get_log(
+        repo.path(),
+        &["-n", "1", "--date=format:%Y-%m-%d"]
+    )));
+
+    set_config(repo.path(), "date_format", "short");
+    assert!(is_short(&get_log(repo.path(), &["-n", "1"])));
+    // The option overrides the setting.
+    assert!(!is_short(&get_log(repo.path(), &["-n", "1", "--date=iso"])));
+
+    let mut cmd = get_repo_cmd(repo.path());
+    cmd.args(["config", "date_format", "format:%Q"]);
+    cmd.assert().failure().code(42);
+    let mut cmd = get_repo_cmd(repo.path());
+    cmd.args(["log", "--date=never"]);
+    cmd.assert().failure().code(2);
+    repo.close().unwrap();
+}
+
+#[test]
+fn test_follow() {
+    let repo = create_test_repo(&[], &[]);
+    init_repo(repo.path());
+    repo.child("a").write_str("1\n2\n3\n4\n").unwrap();
+    repo.child("o").write_str("o\n").unwrap();
+    make_commit(repo.path(), "add a");
+    repo.child("a").write_str("1\n2\n3\n4\n5\n").unwrap();
+    make_commit(repo.path(), "change a");
+    std::fs::rename(repo.child("a"), repo.child("b")).unwrap();
+    make_commit(repo.path(), "rename a to b");
+    std::fs::remove_file(repo.child("b")).unwrap();
+    repo.child("dir/c").write_str("1\n2\n3\n4\nfive\n").unwrap();
+    make_commit(repo.path(), "move b to dir/c");
+    // A removed file with different contents is not a rename.
+    std::fs::remove_file(repo.child("o")).unwrap();
+    repo.child("p").write_str("p\n").unwrap();
+    make_commit(repo.path(), "replace o with p");
+
+    assert_eq!(
+        get_messages(repo.path(), &["--", "dir/c"]),
+        ["move b to dir/c"]
+    );
+    assert_eq!(
+        get_messages(&repo.path().join("dir"), &["--follow", "--", "c"]),
+        ["move b to dir/c", "rename a to b", "change a", "add a"]
+    );
+    assert_eq!(
+        get_messages(
+            repo.path(),
+            &["--follow", "--grep", "^change", "--", "dir/c"]
+        ),
+        ["change a"]
+    );
+    assert_eq!(
+        get_messages(repo.path(), &["--follow", "--", "p"]),
+        ["replace o with p"]
+    );
+
+    let mut cmd = get_repo_cmd(repo.path());
+    cmd.args(["log", "--follow", "--", "a", "b"]);
+    cmd.assert().failure().code(2);
+    let mut cmd = get_repo_cmd(repo.path());
+    cmd.args(["log", "--follow"]);
+    cmd.assert().failure().code(2);
+    repo.close().unwrap();
+}
+
+#[test]
+fn test_follow_large_file() {
+    let repo = create_test_repo(&[], &[]);
+    init_repo(repo.path());
+    set_config(repo.path(), "chunk_threshold", "0");
+    let data = "line\n".repeat(220_000);
+    repo.child("a").write_str(&data).unwrap();
+    repo.child("o").write_str(&(data.clone() + "o\n")).unwrap();
+    make_commit(repo.path(), "add a");
+    // Files too large to be diffed are only renames if they are the same.
+    std::fs::rename(repo.child("a"), repo.child("b")).unwrap();
+    std::fs::remove_file(repo.child("o")).unwrap();
+    repo.child("p").write_str(&(data + "p\n")).unwrap();
+    make_commit(repo.path(), "rename a to b");
+
+    assert_eq!(
+        get_messages(repo.path(), &["--follow", "--", "b"]),
+        ["rename a to b", "add a"]
+    );
+    assert_eq!(
+        get_messages(repo.path(), &["--follow", "--", "p"]),
+        ["rename a to b"]
+    );
+    repo.close().unwrap();
+}
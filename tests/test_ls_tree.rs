@@ -0,0 +1,48 @@
+mod common;
+use common::*;
+
+use predicates::prelude::*;
+
+#[test]
+fn test_list_branch() {
+    let repo = create_test_repo(&["file1", "subdir/file2"], &[]);
+    init_repo(repo.path());
+    let mut cmd = get_repo_cmd(repo.path());
+    cmd.arg("ls-tree").arg("master");
+    cmd.assert().success().stdout(
+        predicate::str::is_match(format!(
+            "^blob {0}\tfile1\ntree {0}\tsubdir\n$",
+            COMMIT_ID_PATTERN
+        ))
+        .unwrap(),
+    );
+    repo.close().unwrap();
+}
+
+#[test]
+fn test_recursive() {
+    let repo = create_test_repo(&["file1", "subdir/file2"], &[]);
+    let commit_id = init_repo(repo.path());
+    let mut cmd = get_repo_cmd(repo.path());
+    cmd.arg("ls-tree").arg("-r").arg(&commit_id);
+    cmd.assert().success().stdout(
+        predicate::str::is_match(format!(
+            "^blob {0}\tfile1\nblob {0}\tsubdir/file2\n$",
+            COMMIT_ID_PATTERN
+        ))
+        .unwrap(),
+    );
+    repo.close().unwrap();
+}
+
+#[test]
+fn test_not_a_tree() {
+    let repo = create_test_repo(&[], &[]);
+    init_repo(repo.path());
+    let mut cmd = get_repo_cmd(repo.path());
+    cmd.arg("ls-tree").arg("no_such_branch");
+    cmd.assert()
+        .failure()
+        .stderr(predicate::str::contains("No object"));
+    repo.close().unwrap();
+}
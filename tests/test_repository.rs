@@ -0,0 +1,83 @@
+use assert_fs::prelude::{FileTouch, FileWriteStr, PathChild};
+use std::path::PathBuf;
+use vcs::{FileStatus, HashAlgorithm, JumpTarget, LogFilter, ObjectStoreKind, Repository};
+
+#[test]
+fn test_repository() {
+    let dir = assert_fs::TempDir::new().unwrap();
+    dir.child("file").touch().unwrap();
+    let (repo, info) =
+        Repository::init(dir.path(), HashAlgorithm::Sha1, ObjectStoreKind::Loose).unwrap();
+    assert_eq!(info.branch, "master");
+    assert_eq!(
+        info.changes,
+        vec![(FileStatus::Added, PathBuf::from("file"))]
+    );
+    assert!(repo.status().unwrap().is_empty());
+
+    let subdir = dir.child("dir");
+    subdir.child("other").touch().unwrap();
+    let repo = Repository::open(subdir.path()).unwrap();
+    assert_eq!(repo.root(), dir.path().canonicalize().unwrap());
+    assert_eq!(
+        repo.status().unwrap(),
+        vec![(FileStatus::Added, PathBuf::from("dir/other"))]
+    );
+    let master_commit = repo.commit("add other").unwrap();
+
+    let state = repo.create_branch("feature").unwrap();
+    assert_eq!(state.branch, "feature");
+    dir.child("file").write_str("feature").unwrap();
+    let feature_commit = repo.commit("modify file").unwrap();
+    let branches: Vec<_> = repo
+        .branches()
+        .unwrap()
+        .into_iter()
+        .map(|branch| (branch.name, branch.head))
+        .collect();
+    assert_eq!(
+        branches,
+        vec![
+            ("feature".to_owned(), feature_commit.human_id.clone()),
+            ("master".to_owned(), master_commit.human_id.clone()),
+        ]
+    );
+
+    let logs = repo.log(&LogFilter::default()).unwrap();
+    assert_eq!(logs[0].human_id, feature_commit.human_id);
+    assert_eq!(logs[0].message, "modify file");
+
+    let state = repo.jump(JumpTarget::Branch("master")).unwrap();
+    assert_eq!(state.commit, Some(master_commit.human_id));
+    assert_eq!(std::fs::read_to_string(dir.child("file")).unwrap(), "");
+
+    let merge_commit = repo.merge("feature").unwrap();
+    assert_eq!(merge_commit.branch, "master");
+    assert_eq!(
+        std::fs::read_to_string(dir.child("file")).unwrap(),
+        "feature"
+    );
+    dir.close().unwrap();
+}
+
+#[test]
+fn test_database_handles() {
+    let dir = assert_fs::TempDir::new().unwrap();
+    dir.child("file").touch().unwrap();
+    let (repo, _) =
+        Repository::init(dir.path(), HashAlgorithm::Sha1, ObjectStoreKind::Database).unwrap();
+    let other = Repository::open(dir.path()).unwrap();
+    dir.child("file").write_str("changed").unwrap();
+    let commit = other.commit("change file").unwrap();
+    assert_eq!(
+        repo.log(&LogFilter::default()).unwrap()[0].human_id,
+        commit.human_id
+    );
+
+    // The database is not kept open between calls, so other commands can use
+    // it while the handles exist.
+    let mut cmd = assert_cmd::Command::cargo_bin("vcs").unwrap();
+    cmd.current_dir(dir.path()).arg("log");
+    cmd.assert().success();
+    dir.close().unwrap();
+}
@@ -1,7 +1,7 @@
 mod common;
 use common::*;
 
-use assert_fs::prelude::{FileWriteStr, PathChild, PathCreateDir};
+use assert_fs::prelude::{FileTouch, FileWriteStr, PathAssert, PathChild, PathCreateDir};
 use predicates::prelude::*;
 
 #[test]
@@ -45,3 +45,91 @@ fn test_changes() {
     );
     repo.close().unwrap();
 }
+
+#[cfg(unix)]
+#[test]
+fn test_mode_change() {
+    use std::os::unix::fs::PermissionsExt;
+    let repo = create_test_repo(&["script.sh"], &[]);
+    init_repo(repo.path());
+
+    let script = repo.child("script.sh");
+    std::fs::set_permissions(script.path(), std::fs::Permissions::from_mode(0o755)).unwrap();
+
+    let mut cmd = get_repo_cmd(repo.path());
+    cmd.arg("status");
+    cmd.assert()
+        .success()
+        .stdout(predicate::str::contains("modified: script.sh"));
+    repo.close().unwrap();
+}
+
+#[test]
+fn test_empty_dirs() {
+    let repo = create_test_repo(&[], &[]);
+    init_repo(repo.path());
+    set_config(repo.path(), "track_empty_dirs", "true");
+    repo.child("logs").create_dir_all().unwrap();
+
+    let mut cmd = get_repo_cmd(repo.path());
+    cmd.arg("status");
+    cmd.assert()
+        .success()
+        .stdout(predicate::str::contains("added: logs"));
+
+    make_commit(repo.path(), "add logs");
+    std::fs::remove_dir(repo.child("logs").path()).unwrap();
+    let mut cmd = get_repo_cmd(repo.path());
+    cmd.arg("status");
+    cmd.assert()
+        .success()
+        .stdout(predicate::str::contains("removed: logs"));
+    repo.close().unwrap();
+}
+
+#[test]
+fn test_untracked_empty_dirs() {
+    let repo = create_test_repo(&[], &[]);
+    init_repo(repo.path());
+    repo.child("a/b").create_dir_all().unwrap();
+    repo.child("file").touch().unwrap();
+    make_commit(repo.path(), "add file");
+    let mut cmd = get_repo_cmd(repo.path());
+    cmd.arg("ls-tree").arg("master");
+    cmd.assert()
+        .success()
+        .stdout(predicate::str::contains("\ta\n").not());
+
+    // Empty directories committed while the setting was on are not tracked
+    // once it is off.
+    set_config(repo.path(), "track_empty_dirs", "true");
+    make_commit(repo.path(), "add a/b");
+    set_config(repo.path(), "track_empty_dirs", "false");
+    create_branch(repo.path(), "other");
+    jump_to_branch(repo.path(), "master");
+    repo.child("a").assert(predicate::path::missing());
+    repo.child(".vcs/index.json")
+        .assert(predicate::str::contains("directory").not());
+    let mut cmd = get_repo_cmd(repo.path());
+    cmd.arg("status");
+    cmd.assert()
+        .success()
+        .stdout(predicate::str::contains("No changes to be committed"));
+    repo.close().unwrap();
+}
+
+#[test]
+fn test_racy_modification() {
+    let repo = create_test_repo(&[], &[]);
+    repo.child("file").write_str("first").unwrap();
+    init_repo(repo.path());
+    // Same size, most likely the same timestamp.
+    repo.child("file").write_str("other").unwrap();
+
+    let mut cmd = get_repo_cmd(repo.path());
+    cmd.arg("status");
+    cmd.assert()
+        .success()
+        .stdout(predicate::str::contains("modified: file"));
+    repo.close().unwrap();
+}